@@ -0,0 +1,440 @@
+//! Helpers for making sense of the instructions in a program: PC-relative
+//! targets, and telling code apart from data by following control flow.
+use super::Loadable;
+
+use lc3_isa::{Addr, Instruction, Reg, Word};
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::convert::TryFrom;
+use std::ops::RangeInclusive;
+
+/// Returns the address a PC-relative instruction at `addr` refers to, if the
+/// instruction is PC-relative.
+///
+/// This is the branch target for `BR` and `JSR` and the address that's read
+/// from or written to for `LD`, `LDI`, `ST`, and `STI` (and computed for
+/// `LEA`).
+pub fn pc_relative_target(addr: Addr, insn: Instruction) -> Option<Addr> {
+    use Instruction::*;
+
+    let offset = match insn {
+        Br { offset9, .. }
+        | Ld { offset9, .. }
+        | Ldi { offset9, .. }
+        | Lea { offset9, .. }
+        | St { offset9, .. }
+        | Sti { offset9, .. } => offset9,
+        Jsr { offset11 } => offset11,
+        _ => return None,
+    };
+
+    // The PC has already been incremented when the offset is applied:
+    Some(addr.wrapping_add(1).wrapping_add(offset as Word))
+}
+
+/// The TRAP vector table and the interrupt vector table; the words here are
+/// the addresses of routines.
+const VECTOR_TABLES: RangeInclusive<Addr> = 0x0000..=0x01FF;
+
+/// How control gets from one basic block to another.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum EdgeKind {
+    /// Into the next word, without branching.
+    Fallthrough,
+    /// Through a (possibly conditional) branch.
+    Branch,
+    /// Through a subroutine call (`JSR` or `JSRR`).
+    Call,
+    /// Through a `TRAP`, to the routine in the TRAP vector table.
+    Trap,
+}
+
+impl EdgeKind {
+    /// Short name of the edge kind.
+    pub fn name(self) -> &'static str {
+        match self {
+            EdgeKind::Fallthrough => "fallthrough",
+            EdgeKind::Branch => "branch",
+            EdgeKind::Call => "call",
+            EdgeKind::Trap => "trap",
+        }
+    }
+}
+
+/// A way out of a basic block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Edge {
+    /// The basic block control goes to.
+    pub to: Addr,
+    /// How it gets there.
+    pub kind: EdgeKind,
+}
+
+/// A run of instructions that's always executed from start to end.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BasicBlock {
+    /// The first instruction in the block.
+    pub start: Addr,
+    /// The last instruction in the block (inclusive).
+    pub end: Addr,
+    /// The blocks control can go to next, as far as we can tell (returns,
+    /// `HALT`, and indirect jumps through registers we can't work out don't
+    /// have any).
+    pub successors: Vec<Edge>,
+}
+
+/// Which words in a program are code (reachable from an entry point) and how
+/// that code breaks up into basic blocks. Everything else is data.
+#[derive(Debug, Clone, Default)]
+pub struct Analysis {
+    code: BTreeSet<Addr>,
+    blocks: BTreeMap<Addr, BasicBlock>,
+}
+
+/// Branches that are never taken (i.e. `BR` with no condition codes) are what
+/// small numbers (and characters) decode as; they're treated as data.
+pub(crate) fn is_never_taken(insn: Instruction) -> bool {
+    matches!(
+        insn,
+        Instruction::Br {
+            n: false,
+            z: false,
+            p: false,
+            ..
+        }
+    )
+}
+
+/// The register an instruction writes to, if any.
+fn destination(insn: Instruction) -> Option<Reg> {
+    use Instruction::*;
+
+    match insn {
+        AddReg { dr, .. }
+        | AddImm { dr, .. }
+        | AndReg { dr, .. }
+        | AndImm { dr, .. }
+        | Ld { dr, .. }
+        | Ldi { dr, .. }
+        | Ldr { dr, .. }
+        | Lea { dr, .. }
+        | Not { dr, .. } => Some(dr),
+        _ => None,
+    }
+}
+
+/// Works out where a `JMP` or `JSRR` at `addr` goes, for the common case where
+/// the straight-line code right before it sets `base` with a `LEA` (or an `LD`
+/// of a word in the program).
+fn indirect_target(addr: Addr, base: Reg, words: &BTreeMap<Addr, Word>) -> Option<Addr> {
+    let mut at = addr;
+
+    loop {
+        at = at.checked_sub(1)?;
+        let insn = Instruction::try_from(*words.get(&at)?).ok()?;
+
+        match insn {
+            Instruction::Lea { dr, .. } if dr == base => return pc_relative_target(at, insn),
+            Instruction::Ld { dr, .. } if dr == base => {
+                return words.get(&pc_relative_target(at, insn)?).copied()
+            }
+            // Anything that changes control flow (or makes the OS run) might
+            // have changed the register:
+            Instruction::Br { .. }
+            | Instruction::Jmp { .. }
+            | Instruction::Jsr { .. }
+            | Instruction::Jsrr { .. }
+            | Instruction::Ret
+            | Instruction::Rti
+            | Instruction::Trap { .. } => return None,
+            _ if destination(insn) == Some(base) => return None,
+            _ => {}
+        }
+    }
+}
+
+/// Where control can go after the instruction at `addr`, and whether the
+/// instruction ends a basic block.
+///
+/// `TRAP`s only end blocks when the program has the routine they go to.
+fn flow(addr: Addr, insn: Instruction, words: &BTreeMap<Addr, Word>) -> (Vec<Edge>, bool) {
+    use Instruction::*;
+
+    let next = Edge {
+        to: addr.wrapping_add(1),
+        kind: EdgeKind::Fallthrough,
+    };
+    let target = |kind| Edge {
+        to: pc_relative_target(addr, insn).unwrap(),
+        kind,
+    };
+    let indirect = |base, kind| indirect_target(addr, base, words).map(|to| Edge { to, kind });
+
+    match insn {
+        Br {
+            n: true,
+            z: true,
+            p: true,
+            ..
+        } => (vec![target(EdgeKind::Branch)], true),
+        Br { .. } => (vec![target(EdgeKind::Branch), next], true),
+        Jsr { .. } => (vec![target(EdgeKind::Call), next], true),
+        Jsrr { base } => (
+            indirect(base, EdgeKind::Call)
+                .into_iter()
+                .chain(Some(next))
+                .collect(),
+            true,
+        ),
+        Jmp { base } => (indirect(base, EdgeKind::Branch).into_iter().collect(), true),
+        Ret | Rti => (vec![], true),
+        Trap { trapvec } => {
+            let routine = words.get(&Addr::from(trapvec)).map(|to| Edge {
+                to: *to,
+                kind: EdgeKind::Trap,
+            });
+
+            match (routine, trapvec) {
+                (routine, 0x25) => (routine.into_iter().collect(), true),
+                (Some(routine), _) => (vec![routine, next], true),
+                (None, _) => (vec![next], false),
+            }
+        }
+        _ => (vec![next], false),
+    }
+}
+
+/// Returns every instruction that's reachable from the entry points, along with
+/// where control goes after it.
+fn descend(
+    words: &BTreeMap<Addr, Word>,
+    entries: &BTreeSet<Addr>,
+) -> BTreeMap<Addr, (Instruction, Vec<Edge>, bool)> {
+    let mut code = BTreeMap::new();
+    let mut pending: Vec<Addr> = entries.iter().copied().collect();
+
+    while let Some(addr) = pending.pop() {
+        if code.contains_key(&addr) || VECTOR_TABLES.contains(&addr) {
+            continue;
+        }
+
+        let insn = match words.get(&addr).map(|w| Instruction::try_from(*w)) {
+            Some(Ok(insn)) if !is_never_taken(insn) => insn,
+            _ => continue,
+        };
+
+        let (edges, ends) = flow(addr, insn, words);
+        pending.extend(edges.iter().map(|e| e.to));
+        let _ = code.insert(addr, (insn, edges, ends));
+    }
+
+    code
+}
+
+impl Analysis {
+    /// Finds the code in a program by following control flow from its entry
+    /// points: the origins of its segments, the routines in its TRAP and
+    /// interrupt vector tables (if it has them), and `extra_entries`.
+    ///
+    /// Segment origins that the code loads from, stores to, or takes the
+    /// address of are assumed to be data instead.
+    pub fn run(loadables: &[Loadable], extra_entries: &[Addr]) -> Self {
+        let words: BTreeMap<Addr, Word> = loadables.iter().copied().collect();
+
+        let mut entries: BTreeSet<Addr> = extra_entries.iter().copied().collect();
+        entries.extend(
+            words
+                .range(VECTOR_TABLES)
+                .map(|(_, w)| *w)
+                .filter(|w| words.contains_key(w)),
+        );
+
+        let origins: BTreeSet<Addr> = words
+            .keys()
+            .copied()
+            .filter(|a| !VECTOR_TABLES.contains(a))
+            .filter(|a| *a == 0 || !words.contains_key(&(a - 1)))
+            .collect();
+
+        let code = descend(&words, &entries.union(&origins).copied().collect());
+        let data: BTreeSet<Addr> = code
+            .iter()
+            .filter(|(_, (insn, ..))| {
+                matches!(
+                    insn,
+                    Instruction::Ld { .. }
+                        | Instruction::Ldi { .. }
+                        | Instruction::Lea { .. }
+                        | Instruction::St { .. }
+                        | Instruction::Sti { .. }
+                )
+            })
+            .filter_map(|(addr, (insn, ..))| pc_relative_target(*addr, *insn))
+            .collect();
+
+        entries.extend(origins.difference(&data));
+        let code = descend(&words, &entries);
+
+        // Blocks start at entry points, at branch and call targets, and after
+        // instructions that end blocks:
+        let mut leaders: BTreeSet<Addr> = entries.clone();
+        for (addr, (_, edges, _)) in code.iter().filter(|(_, (_, _, ends))| *ends) {
+            leaders.extend(edges.iter().map(|e| e.to));
+            let _ = leaders.insert(addr.wrapping_add(1));
+        }
+        leaders.retain(|a| code.contains_key(a));
+
+        let mut blocks = BTreeMap::new();
+        for start in leaders.iter().copied() {
+            let mut end = start;
+
+            let successors = loop {
+                let (_, edges, ends) = &code[&end];
+                let next = end.wrapping_add(1);
+
+                if *ends {
+                    break edges.clone();
+                } else if leaders.contains(&next) {
+                    break vec![Edge {
+                        to: next,
+                        kind: EdgeKind::Fallthrough,
+                    }];
+                } else if !code.contains_key(&next) {
+                    break vec![];
+                }
+
+                end = next;
+            };
+
+            let successors = successors
+                .into_iter()
+                .filter(|e| code.contains_key(&e.to))
+                .collect();
+
+            let _ = blocks.insert(
+                start,
+                BasicBlock {
+                    start,
+                    end,
+                    successors,
+                },
+            );
+        }
+
+        Analysis {
+            code: code.keys().copied().collect(),
+            blocks,
+        }
+    }
+
+    /// Whether the word at the given address is code.
+    pub fn is_code(&self, addr: Addr) -> bool {
+        self.code.contains(&addr)
+    }
+
+    /// Every basic block, in address order.
+    pub fn blocks(&self) -> impl Iterator<Item = &BasicBlock> {
+        self.blocks.values()
+    }
+
+    /// The basic block that starts at the given address, if there is one.
+    pub fn block_at(&self, addr: Addr) -> Option<&BasicBlock> {
+        self.blocks.get(&addr)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn program(orig: Addr, words: &[Word]) -> Vec<Loadable> {
+        (orig..).zip(words.iter().copied()).collect()
+    }
+
+    #[test]
+    fn data_is_not_reachable() {
+        let words = [
+            0xE004, // LEA R0, MSG
+            0xF022, // PUTS
+            0x0E01, // BRnzp DONE
+            0x0041, // .FILL 'A'
+            0xF025, // DONE: HALT
+            0x0048, // MSG: .STRINGZ "H"
+            0x0000,
+        ];
+        let analysis = Analysis::run(&program(0x3000, &words), &[]);
+
+        let code: Vec<_> = (0x3000..0x3007).filter(|a| analysis.is_code(*a)).collect();
+        assert_eq!(code, [0x3000, 0x3001, 0x3002, 0x3004]);
+
+        let blocks: Vec<_> = analysis.blocks().collect();
+        assert_eq!(
+            blocks,
+            [
+                &BasicBlock {
+                    start: 0x3000,
+                    end: 0x3002,
+                    successors: vec![Edge {
+                        to: 0x3004,
+                        kind: EdgeKind::Branch
+                    }],
+                },
+                &BasicBlock {
+                    start: 0x3004,
+                    end: 0x3004,
+                    successors: vec![],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn calls_return_to_the_next_word() {
+        let words = [
+            0x4802, // JSR SUB
+            0xF025, // HALT
+            0x0000, // .FILL 0
+            0xC1C0, // SUB: RET
+        ];
+        let analysis = Analysis::run(&program(0x3000, &words), &[]);
+
+        assert!(!analysis.is_code(0x3002));
+        assert!(analysis.is_code(0x3003));
+        assert_eq!(
+            analysis.block_at(0x3000).unwrap().successors,
+            [
+                Edge {
+                    to: 0x3003,
+                    kind: EdgeKind::Call
+                },
+                Edge {
+                    to: 0x3001,
+                    kind: EdgeKind::Fallthrough
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn segments_that_are_loaded_from_are_data() {
+        let mut loadables = program(0x3000, &[0x200F, 0xF025]); // LD R0, VALUE; HALT
+        loadables.extend(program(0x3010, &[0x1021])); // VALUE: .FILL x1021
+        loadables.extend(program(0x3020, &[0x1021, 0xF025]));
+
+        let analysis = Analysis::run(&loadables, &[]);
+        assert!(!analysis.is_code(0x3010));
+        assert!(analysis.is_code(0x3020) && analysis.is_code(0x3021));
+
+        let analysis = Analysis::run(&loadables, &[0x3010]);
+        assert!(analysis.is_code(0x3010));
+    }
+
+    #[test]
+    fn pc_relative_targets() {
+        let lea = Instruction::try_from(0xE1FF).unwrap(); // LEA R0, #-1
+        assert_eq!(pc_relative_target(0x3000, lea), Some(0x3000));
+
+        let add = Instruction::try_from(0x1021).unwrap();
+        assert_eq!(pc_relative_target(0x3000, add), None);
+    }
+}
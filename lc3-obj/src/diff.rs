@@ -0,0 +1,184 @@
+//! Comparing two programs word by word: which addresses one has words for
+//! that the other doesn't, and which addresses they have different words for.
+use super::disasm::disassemble;
+use super::validation::range;
+use super::Loadable;
+
+use lc3_isa::{Addr, Word};
+
+use std::collections::BTreeMap;
+use std::fmt::{self, Display};
+use std::ops::RangeInclusive;
+
+/// How an address differs between the two programs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ChangeKind {
+    /// Only the new program has a word here.
+    Added,
+    /// Only the old program has a word here.
+    Removed,
+    /// Both programs have a word here but the words are different.
+    Changed,
+}
+
+impl ChangeKind {
+    fn of(old: Option<Word>, new: Option<Word>) -> Option<Self> {
+        match (old, new) {
+            (None, Some(_)) => Some(ChangeKind::Added),
+            (Some(_), None) => Some(ChangeKind::Removed),
+            (Some(o), Some(n)) if o != n => Some(ChangeKind::Changed),
+            _ => None,
+        }
+    }
+}
+
+/// A run of contiguous addresses that differ in the same way.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hunk {
+    /// How the addresses differ.
+    pub kind: ChangeKind,
+    /// The addresses.
+    pub range: RangeInclusive<Addr>,
+    /// The old and new words at each address in the range, in order.
+    pub words: Vec<(Option<Word>, Option<Word>)>,
+}
+
+impl Display for Hunk {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (sigil, what) = match self.kind {
+            ChangeKind::Added => ('+', "added"),
+            ChangeKind::Removed => ('-', "removed"),
+            ChangeKind::Changed => ('~', "changed"),
+        };
+
+        writeln!(fmt, "{} {} {}", sigil, range(&self.range), what)?;
+
+        self.words
+            .iter()
+            .enumerate()
+            .try_for_each(|(idx, (old, new))| {
+                let addr = self.range.start().wrapping_add(idx as Addr);
+                let side = |word: &Option<Word>| match word {
+                    Some(w) => format!(
+                        "{:04X}  {}",
+                        w,
+                        disassemble(addr, *w).unwrap_or_else(|| format!(".FILL x{:04X}", w))
+                    ),
+                    None => String::new(),
+                };
+
+                match self.kind {
+                    ChangeKind::Added => writeln!(fmt, "    x{:04X}: {}", addr, side(new)),
+                    ChangeKind::Removed => writeln!(fmt, "    x{:04X}: {}", addr, side(old)),
+                    ChangeKind::Changed => {
+                        writeln!(fmt, "    x{:04X}: {:<26} -> {}", addr, side(old), side(new))
+                    }
+                }
+            })
+    }
+}
+
+/// Compares two programs, returning every difference between them (sorted by
+/// address) with contiguous addresses that differ in the same way grouped
+/// together.
+///
+/// Where a program has more than one word for an address, the last one wins
+/// (as it would when loading the program).
+pub fn diff(old: &[Loadable], new: &[Loadable]) -> Vec<Hunk> {
+    let old: BTreeMap<Addr, Word> = old.iter().copied().collect();
+    let new: BTreeMap<Addr, Word> = new.iter().copied().collect();
+
+    let mut addrs: Vec<Addr> = old.keys().chain(new.keys()).copied().collect();
+    addrs.sort();
+    addrs.dedup();
+
+    let mut hunks: Vec<Hunk> = Vec::new();
+
+    for addr in addrs {
+        let (o, n) = (old.get(&addr).copied(), new.get(&addr).copied());
+        let kind = match ChangeKind::of(o, n) {
+            Some(kind) => kind,
+            None => continue,
+        };
+
+        match hunks.last_mut() {
+            Some(h) if h.kind == kind && u32::from(*h.range.end()) + 1 == u32::from(addr) => {
+                h.range = *h.range.start()..=addr;
+                h.words.push((o, n));
+            }
+            _ => hunks.push(Hunk {
+                kind,
+                range: addr..=addr,
+                words: vec![(o, n)],
+            }),
+        }
+    }
+
+    hunks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hunks() {
+        let old = [
+            (0x3000, 0x1021),
+            (0x3001, 0x1021),
+            (0x3002, 0xF025),
+            (0x4000, 1),
+        ];
+        let new = [
+            (0x3000, 0x1021),
+            (0x3001, 0x1261),
+            (0x3002, 0xF022),
+            (0x3003, 0xF025),
+        ];
+
+        assert_eq!(
+            diff(&old, &new),
+            vec![
+                Hunk {
+                    kind: ChangeKind::Changed,
+                    range: 0x3001..=0x3002,
+                    words: vec![(Some(0x1021), Some(0x1261)), (Some(0xF025), Some(0xF022))],
+                },
+                Hunk {
+                    kind: ChangeKind::Added,
+                    range: 0x3003..=0x3003,
+                    words: vec![(None, Some(0xF025))],
+                },
+                Hunk {
+                    kind: ChangeKind::Removed,
+                    range: 0x4000..=0x4000,
+                    words: vec![(Some(1), None)],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn the_last_word_for_an_address_wins() {
+        let old = [(0x3000, 1), (0x3000, 2)];
+
+        assert_eq!(diff(&old, &[(0x3000, 2)]), vec![]);
+        assert_eq!(diff(&old, &[(0x3000, 1)]).len(), 1);
+    }
+
+    #[test]
+    fn output() {
+        let hunks = diff(&[(0x3000, 1), (0x3001, 2)], &[(0x3000, 3)]);
+        let text: Vec<String> = hunks.iter().map(|h| h.to_string()).collect();
+
+        let changed: Vec<&str> = text[0].lines().collect();
+        assert_eq!(changed.len(), 2);
+        assert_eq!(changed[0], "~ x3000 changed");
+        assert!(changed[1].starts_with("    x3000: 0001  "));
+        assert!(changed[1].contains(" -> 0003  "));
+
+        let removed: Vec<&str> = text[1].lines().collect();
+        assert_eq!(removed[0], "- x3001 removed");
+        assert!(removed[1].starts_with("    x3001: 0002  "));
+    }
+}
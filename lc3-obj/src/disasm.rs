@@ -0,0 +1,291 @@
+//! Turning programs back into assembly that can be assembled again.
+//!
+//! Every segment gets its own `.ORIG`/`.END` block. Words that the
+//! [analysis](crate::analysis::Analysis) found to be code are written out as
+//! instructions; data is written out as `.FILL`s, except for runs of ASCII
+//! characters that end in a NUL, which become `.STRINGZ`s. PC-relative
+//! operands refer to labels (from the symbol table if there is one, otherwise
+//! synthesized) instead of raw offsets whenever the target is part of the
+//! program.
+use super::analysis::{is_never_taken, pc_relative_target, Analysis};
+use super::file_formats::{segments, Object};
+
+use lc3_isa::{Addr, Instruction, Word};
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::convert::TryFrom;
+use std::io::{Result as IoResult, Write};
+
+/// Human readable name of the output.
+pub const NAME: &str = "an LC-3 Assembly File";
+
+/// What a word (or run of words) is written out as.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Item {
+    Insn(Instruction),
+    Fill(Word),
+    /// The characters in the string; the string (and its NUL) spans
+    /// `len() + 1` words.
+    Stringz(String),
+}
+
+/// Whether a word holds a character that can go in a `.STRINGZ`.
+fn as_char(word: Word) -> Option<char> {
+    match word {
+        0x20..=0x7E | 0x09 | 0x0A | 0x0D => Some(word as u8 as char),
+        _ => None,
+    }
+}
+
+/// Escapes a string so it can go between quotes in a `.STRINGZ`.
+fn escape(s: &str) -> String {
+    s.chars()
+        .map(|c| match c {
+            '\n' => "\\n".to_string(),
+            '\t' => "\\t".to_string(),
+            '\r' => "\\r".to_string(),
+            '"' => "\\\"".to_string(),
+            '\\' => "\\\\".to_string(),
+            c => c.to_string(),
+        })
+        .collect()
+}
+
+/// Returns the name of the TRAP routine with the given vector, for the ones
+/// that have assembler aliases.
+pub(crate) fn trap_alias(trapvec: u8) -> Option<&'static str> {
+    match trapvec {
+        0x20 => Some("GETC"),
+        0x21 => Some("OUT"),
+        0x22 => Some("PUTS"),
+        0x23 => Some("IN"),
+        0x24 => Some("PUTSP"),
+        0x25 => Some("HALT"),
+        _ => None,
+    }
+}
+
+/// Decides what every word in a segment is written out as.
+///
+/// `strings` is the set of addresses that are `LEA` targets.
+fn classify(
+    orig: Addr,
+    words: &[Word],
+    analysis: &Analysis,
+    strings: &BTreeSet<Addr>,
+) -> Vec<(Addr, Item)> {
+    let mut items = Vec::new();
+    let mut idx = 0;
+
+    while idx < words.len() {
+        let addr = orig.wrapping_add(idx as Addr);
+        let is_data = |idx: usize| !analysis.is_code(orig.wrapping_add(idx as Addr));
+
+        // A run of characters followed by a NUL is a string if it's at least 2
+        // characters long or if it's used as one:
+        let chars: String = words[idx..]
+            .iter()
+            .enumerate()
+            .take_while(|(i, _)| is_data(idx + i))
+            .map(|(_, w)| as_char(*w))
+            .take_while(Option::is_some)
+            .flatten()
+            .collect();
+        let end = idx + chars.len();
+        let terminated = words.get(end) == Some(&0) && is_data(end);
+
+        if terminated && (chars.len() >= 2 || (chars.len() == 1 && strings.contains(&addr))) {
+            idx += chars.len() + 1;
+            items.push((addr, Item::Stringz(chars)));
+            continue;
+        }
+
+        let word = words[idx];
+        let item = match Instruction::try_from(word) {
+            Ok(insn) if analysis.is_code(addr) => Item::Insn(insn),
+            _ => Item::Fill(word),
+        };
+
+        items.push((addr, item));
+        idx += 1;
+    }
+
+    items
+}
+
+/// Writes an instruction out as assembly; `target` gives the operand to use
+/// for a PC-relative instruction's target.
+pub(crate) fn format_insn(insn: Instruction, target: &dyn Fn() -> String) -> String {
+    use Instruction::*;
+
+    match insn {
+        AddReg { dr, sr1, sr2 } => format!("ADD {}, {}, {}", dr, sr1, sr2),
+        AddImm { dr, sr1, imm5 } => format!("ADD {}, {}, #{}", dr, sr1, imm5),
+        AndReg { dr, sr1, sr2 } => format!("AND {}, {}, {}", dr, sr1, sr2),
+        AndImm { dr, sr1, imm5 } => format!("AND {}, {}, #{}", dr, sr1, imm5),
+        Br { n, z, p, .. } => format!(
+            "BR{}{}{} {}",
+            if n { "n" } else { "" },
+            if z { "z" } else { "" },
+            if p { "p" } else { "" },
+            target()
+        ),
+        Jmp { base } => format!("JMP {}", base),
+        Jsr { .. } => format!("JSR {}", target()),
+        Jsrr { base } => format!("JSRR {}", base),
+        Ld { dr, .. } => format!("LD {}, {}", dr, target()),
+        Ldi { dr, .. } => format!("LDI {}, {}", dr, target()),
+        Ldr { dr, base, offset6 } => format!("LDR {}, {}, #{}", dr, base, offset6),
+        Lea { dr, .. } => format!("LEA {}, {}", dr, target()),
+        Not { dr, sr } => format!("NOT {}, {}", dr, sr),
+        Ret => "RET".to_string(),
+        Rti => "RTI".to_string(),
+        St { sr, .. } => format!("ST {}, {}", sr, target()),
+        Sti { sr, .. } => format!("STI {}, {}", sr, target()),
+        Str { sr, base, offset6 } => format!("STR {}, {}, #{}", sr, base, offset6),
+        Trap { trapvec } => match trap_alias(trapvec) {
+            Some(alias) => alias.to_string(),
+            None => format!("TRAP x{:02X}", trapvec),
+        },
+    }
+}
+
+/// Disassembles a single word on its own, with PC-relative operands as
+/// absolute addresses. Returns `None` for words that aren't instructions (or
+/// that are branches that are never taken, which are almost always data).
+pub fn disassemble(addr: Addr, word: Word) -> Option<String> {
+    match Instruction::try_from(word) {
+        Ok(insn) if !is_never_taken(insn) => Some(format_insn(insn, &|| {
+            format!("x{:04X}", pc_relative_target(addr, insn).unwrap())
+        })),
+        _ => None,
+    }
+}
+
+/// Writes the program out as assembly, using the analysis to tell code apart
+/// from data.
+pub fn write<W: Write>(object: &Object, analysis: &Analysis, file: &mut W) -> IoResult<()> {
+    let words: BTreeMap<Addr, Word> = object.loadables.iter().copied().collect();
+
+    // Every PC-relative reference the code makes, for labels and strings:
+    let mut strings = BTreeSet::new();
+    let mut targets = BTreeSet::new();
+
+    for (addr, word) in words.iter().filter(|(a, _)| analysis.is_code(**a)) {
+        if let Ok(insn) = Instruction::try_from(*word) {
+            if let Some(target) = pc_relative_target(*addr, insn) {
+                let _ = targets.insert(target);
+
+                if let Instruction::Lea { .. } = insn {
+                    let _ = strings.insert(target);
+                }
+            }
+        }
+    }
+
+    let segments = segments(words.iter().map(|(a, w)| (*a, *w)));
+    let items: Vec<Vec<(Addr, Item)>> = segments
+        .iter()
+        .map(|(orig, words)| classify(*orig, words, analysis, &strings))
+        .collect();
+
+    // Only addresses that something starts at can have labels:
+    let starts: BTreeSet<Addr> = items.iter().flatten().map(|(a, _)| *a).collect();
+    let labels: BTreeMap<Addr, String> = starts
+        .iter()
+        .filter_map(|addr| match object.symbols.label_for(*addr) {
+            Some(label) => Some((*addr, label.to_string())),
+            None if targets.contains(addr) => Some((*addr, format!("L_{:04X}", addr))),
+            None => None,
+        })
+        .collect();
+
+    writeln!(file, "; Disassembled by obj-conv.")?;
+
+    for ((orig, _), items) in segments.iter().zip(items) {
+        writeln!(file)?;
+        writeln!(file, "{:<15} .ORIG x{:04X}", "", orig)?;
+
+        for (addr, item) in items {
+            let statement = match item {
+                Item::Insn(insn) => format_insn(insn, &|| {
+                    let target = pc_relative_target(addr, insn).unwrap();
+
+                    match labels.get(&target) {
+                        Some(label) => label.clone(),
+                        None => format!("#{}", target.wrapping_sub(addr.wrapping_add(1)) as i16),
+                    }
+                }),
+                Item::Fill(word) => format!(".FILL x{:04X}", word),
+                Item::Stringz(s) => format!(".STRINGZ \"{}\"", escape(&s)),
+            };
+
+            let label = labels.get(&addr).map(String::as_str).unwrap_or_default();
+            let comment = object
+                .source_lines
+                .get(&addr)
+                .map(|l| l.text.trim())
+                .filter(|l| !l.is_empty())
+                .map(|l| format!(" ; {}", l))
+                .unwrap_or_default();
+
+            let line = format!("{:<15} {:<24}{}", label, statement, comment);
+            writeln!(file, "{}", line.trim_end())?;
+        }
+
+        writeln!(file, "{:<15} .END", "")?;
+    }
+
+    file.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn segments_labels_strings_and_data() {
+        let mut loadables: Vec<(Addr, Word)> = (0x3000..)
+            .zip(vec![
+                0xE004, // LEA R0, MSG
+                0xF022, // PUTS
+                0x0401, // BRz DONE
+                0x2208, // LD R1, DATA
+                0xF025, // DONE: HALT
+            ])
+            .collect();
+        // MSG: .STRINGZ "a\"b\\c\n"
+        loadables.extend((0x3005..).zip("a\"b\\c\n\0".bytes().map(Word::from)));
+        loadables.push((0x300C, 0x1234)); // DATA
+        loadables.extend(vec![(0x4000, 0x1021), (0x4001, 0xC1C0)]);
+
+        let object = Object::from(loadables);
+        let analysis = Analysis::run(&object.loadables, &[]);
+
+        let mut out = Vec::new();
+        write(&object, &analysis, &mut out).unwrap();
+
+        let text = String::from_utf8(out).unwrap();
+        assert_eq!(
+            text.lines().collect::<Vec<_>>(),
+            [
+                "; Disassembled by obj-conv.",
+                "",
+                "                .ORIG x3000",
+                "                LEA R0, L_3005",
+                "                PUTS",
+                "                BRz L_3004",
+                "                LD R1, L_300C",
+                "L_3004          HALT",
+                r#"L_3005          .STRINGZ "a\"b\\c\n""#,
+                "L_300C          .FILL x1234",
+                "                .END",
+                "",
+                "                .ORIG x4000",
+                "                ADD R0, R0, #1",
+                "                RET",
+                "                .END",
+            ]
+        );
+    }
+}
@@ -0,0 +1,113 @@
+//! Exporting a program's control-flow graph as a [Graphviz](https://graphviz.org)
+//! DOT file: a node for every basic block (listing its instructions) and an
+//! edge for every way control can get from one block to another.
+//!
+//! Fallthrough edges are dashed; the others are labelled with how control gets
+//! across (a branch, a call, or a `TRAP`).
+use super::analysis::{pc_relative_target, Analysis, EdgeKind};
+use super::disasm::format_insn;
+use super::file_formats::Object;
+
+use lc3_isa::{Addr, Instruction, Word};
+
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::io::{Result as IoResult, Write};
+
+/// Human readable name of the output.
+pub const NAME: &str = "a Graphviz DOT Control-Flow Graph";
+
+/// Escapes a string so it can go between quotes in a DOT file.
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Writes the program's control-flow graph out as DOT, using the analysis for
+/// the basic blocks.
+pub fn write<W: Write>(object: &Object, analysis: &Analysis, file: &mut W) -> IoResult<()> {
+    let words: BTreeMap<Addr, Word> = object.loadables.iter().copied().collect();
+
+    let name = |addr: Addr| match object.symbols.label_for(addr) {
+        Some(label) => label.to_string(),
+        None => format!("x{:04X}", addr),
+    };
+
+    writeln!(file, "digraph cfg {{")?;
+    writeln!(file, "    node [shape=box, fontname=\"monospace\"];")?;
+
+    for block in analysis.blocks() {
+        let mut label = String::new();
+
+        for addr in (0..=block.end.wrapping_sub(block.start)).map(|i| block.start.wrapping_add(i)) {
+            if let Some(l) = object.symbols.label_for(addr) {
+                label.push_str(&format!("{}:\\l", escape(l)));
+            }
+
+            // Everything in a block is code, which always decodes:
+            let insn = Instruction::try_from(words[&addr]).unwrap();
+            let insn = format_insn(insn, &|| name(pc_relative_target(addr, insn).unwrap()));
+
+            label.push_str(&format!("x{:04X}  {}\\l", addr, escape(&insn)));
+        }
+
+        writeln!(file, "    \"x{:04X}\" [label=\"{}\"];", block.start, label)?;
+
+        for edge in block.successors.iter() {
+            let attrs = match edge.kind {
+                EdgeKind::Fallthrough => "style=dashed".to_string(),
+                kind => format!("label=\"{}\"", kind.name()),
+            };
+
+            writeln!(
+                file,
+                "    \"x{:04X}\" -> \"x{:04X}\" [{}];",
+                block.start, edge.to, attrs
+            )?;
+        }
+    }
+
+    writeln!(file, "}}")?;
+    file.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blocks_and_edges() {
+        let words = vec![
+            0x0402, // BRz x3003
+            0x4802, // JSR SUB
+            0x1021, // ADD R0, R0, #1
+            0xF025, // HALT
+            0xC1C0, // SUB: RET
+        ];
+        let mut object = Object::from((0x3000..).zip(words).collect::<Vec<_>>());
+        object.symbols.insert("SUB", 0x3004);
+        let analysis = Analysis::run(&object.loadables, &[]);
+
+        let mut out = Vec::new();
+        write(&object, &analysis, &mut out).unwrap();
+
+        let text = String::from_utf8(out).unwrap();
+        assert_eq!(
+            text.lines().collect::<Vec<_>>(),
+            [
+                "digraph cfg {",
+                r#"    node [shape=box, fontname="monospace"];"#,
+                r#"    "x3000" [label="x3000  BRz x3003\l"];"#,
+                r#"    "x3000" -> "x3003" [label="branch"];"#,
+                r#"    "x3000" -> "x3001" [style=dashed];"#,
+                r#"    "x3001" [label="x3001  JSR SUB\l"];"#,
+                r#"    "x3001" -> "x3004" [label="call"];"#,
+                r#"    "x3001" -> "x3002" [style=dashed];"#,
+                r#"    "x3002" [label="x3002  ADD R0, R0, #1\l"];"#,
+                r#"    "x3002" -> "x3003" [style=dashed];"#,
+                r#"    "x3003" [label="x3003  HALT\l"];"#,
+                r#"    "x3004" [label="SUB:\lx3004  RET\l"];"#,
+                "}",
+            ]
+        );
+    }
+}
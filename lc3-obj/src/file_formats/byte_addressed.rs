@@ -0,0 +1,110 @@
+//! Helpers for formats that are byte addressed (i.e. Intel HEX and Motorola
+//! S-records).
+//!
+//! The LC-3 is word addressed; in byte addressed formats the word at address
+//! `a` occupies bytes `2a` and `2a + 1`, high byte first (i.e. words are big
+//! endian).
+use super::{Loadable, Location, ParseError, ParseResult};
+
+use lc3_isa::Addr;
+
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::{format, vec, vec::Vec};
+
+/// Splits loadables into (byte address, byte) pairs.
+pub(super) fn to_bytes(loadables: &[Loadable]) -> Vec<(u32, u8)> {
+    loadables
+        .iter()
+        .flat_map(|(addr, word)| {
+            let byte_addr = u32::from(*addr) * 2;
+            let [hi, lo] = word.to_be_bytes();
+
+            vec![(byte_addr, hi), (byte_addr + 1, lo)]
+        })
+        .collect()
+}
+
+/// Adds a record's data, starting at the given byte address, to a map of
+/// (byte address, byte) pairs.
+///
+/// Errors if any of the data is outside of the LC-3's address space.
+pub(super) fn insert_bytes(
+    bytes: &mut BTreeMap<u32, u8>,
+    addr: u32,
+    data: &[u8],
+    at: Location,
+) -> ParseResult<()> {
+    let end = u64::from(addr) + data.len() as u64;
+    if end > (u64::from(Addr::MAX) + 1) * 2 {
+        return Err(ParseError::AddressOverflow { at });
+    }
+
+    data.iter().enumerate().for_each(|(i, b)| {
+        let _ = bytes.insert(addr + i as u32, *b);
+    });
+
+    Ok(())
+}
+
+/// Reassembles words from (byte address, byte) pairs.
+///
+/// Errors if a word is only partially specified. The bytes are expected to be
+/// within the LC-3's address space (see [`insert_bytes`](insert_bytes)).
+pub(super) fn from_bytes(bytes: &BTreeMap<u32, u8>) -> ParseResult<Vec<Loadable>> {
+    let mut loadables = Vec::with_capacity(bytes.len() / 2);
+    let mut iter = bytes.iter();
+
+    while let Some((&byte_addr, &hi)) = iter.next() {
+        if byte_addr % 2 != 0 {
+            return Err(ParseError::PartialWord { byte_addr });
+        }
+
+        let lo = match iter.next() {
+            Some((&a, &lo)) if a == byte_addr + 1 => lo,
+            _ => return Err(ParseError::PartialWord { byte_addr }),
+        };
+
+        loadables.push(((byte_addr / 2) as Addr, u16::from_be_bytes([hi, lo])));
+    }
+
+    Ok(loadables)
+}
+
+/// Groups (byte address, byte) pairs into runs of contiguous bytes that are at
+/// most `max_len` long and that don't cross a 64KiB boundary.
+pub(super) fn records(bytes: &[(u32, u8)], max_len: usize) -> Vec<(u32, Vec<u8>)> {
+    let mut records: Vec<(u32, Vec<u8>)> = Vec::new();
+
+    for &(addr, byte) in bytes {
+        match records.last_mut() {
+            Some((start, data))
+                if data.len() < max_len
+                    && *start + data.len() as u32 == addr
+                    && addr % 0x1_0000 != 0 =>
+            {
+                data.push(byte)
+            }
+            _ => records.push((addr, vec![byte])),
+        }
+    }
+
+    records
+}
+
+/// Decodes a string of hex digit pairs.
+pub(super) fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) || !hex.is_ascii() {
+        return None;
+    }
+
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok())
+        .collect()
+}
+
+/// Encodes bytes as a string of (uppercase) hex digit pairs.
+pub(super) fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02X}", b)).collect()
+}
@@ -0,0 +1,82 @@
+//! Debug info sidecars (`.dbg` files): the source line behind every word of a
+//! program, for simulators and debuggers that only get a memory image.
+//!
+//! These are text files with a tab separated line for every word that has a
+//! source line (the tabs are shown as spaces here):
+//! ```text
+//! ; obj-conv debug info
+//! ; address    ordinal    source line
+//! x3000    2    ADD R0, R0, #1
+//! x3001    3    HALT
+//! ```
+//!
+//! The ordinal is where the line was in the object file it came from; LC3Tools
+//! writes lines in the order they appear in the source, so sorting on it puts
+//! the lines back in source order.
+#[cfg(feature = "std")]
+use super::IoResult;
+
+#[cfg(feature = "std")]
+use lc3_isa::Addr;
+
+use alloc::string::String;
+
+#[cfg(feature = "std")]
+use std::collections::BTreeMap;
+#[cfg(feature = "std")]
+use std::io::Write;
+
+/// The source line a word was assembled from.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SourceLine {
+    /// The line, as it was written.
+    pub text: String,
+    /// Where the line was in the object file (starting from 1).
+    pub ordinal: usize,
+}
+
+/// Writes out a debug info sidecar for the given source lines. Empty lines
+/// are left out.
+#[cfg(feature = "std")]
+pub fn write<W: Write>(lines: &BTreeMap<Addr, SourceLine>, file: &mut W) -> IoResult<()> {
+    writeln!(file, "; obj-conv debug info")?;
+    writeln!(file, "; address\tordinal\tsource line")?;
+
+    lines
+        .iter()
+        .filter(|(_, l)| !l.text.trim().is_empty())
+        .try_for_each(|(addr, l)| {
+            writeln!(file, "x{:04X}\t{}\t{}", addr, l.ordinal, l.text.trim())
+        })?;
+
+    file.flush()
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_lines_are_left_out() {
+        let line = |text: &str, ordinal| SourceLine {
+            text: text.to_string(),
+            ordinal,
+        };
+        let lines = vec![
+            (0x3000, line("  ADD R0, R0, #1 ", 2)),
+            (0x3001, line("", 3)),
+            (0x3002, line("HALT", 4)),
+        ];
+
+        let mut out = Vec::new();
+        write(&lines.into_iter().collect(), &mut out).unwrap();
+
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "; obj-conv debug info\n\
+             ; address\tordinal\tsource line\n\
+             x3000\t2\tADD R0, R0, #1\n\
+             x3002\t4\tHALT\n"
+        );
+    }
+}
@@ -0,0 +1,215 @@
+//! The errors parsing (i.e. [`ObjFileFormat::parse_bytes`]) and writing (i.e.
+//! [`ObjFileWriter::write_bytes`]) object files can return.
+//!
+//! [`ObjFileFormat::parse_bytes`]: super::ObjFileFormat::parse_bytes
+//! [`ObjFileWriter::write_bytes`]: super::ObjFileWriter::write_bytes
+use alloc::string::String;
+use core::fmt::{self, Display};
+
+#[cfg(feature = "std")]
+use std::error;
+#[cfg(feature = "std")]
+use std::io::{Error, ErrorKind};
+
+/// Where in a file something went wrong.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Location {
+    /// A byte offset, for binary formats.
+    Byte(u64),
+    /// A (1 indexed) line number, for text formats.
+    Line(usize),
+    /// A (0 indexed) word in a (0 indexed) segment, for structured formats.
+    Word {
+        /// Which segment the word is in.
+        segment: usize,
+        /// Which word in the segment it is.
+        word: usize,
+    },
+}
+
+impl Display for Location {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Location::Byte(offset) => write!(fmt, "byte {:#X}", offset),
+            Location::Line(line) => write!(fmt, "line {}", line),
+            Location::Word { segment, word } => {
+                write!(fmt, "segments[{}].words[{}]", segment, word)
+            }
+        }
+    }
+}
+
+/// Ways parsing an object file can fail.
+#[derive(Debug)]
+pub enum ParseError {
+    /// Reading the file failed.
+    #[cfg(feature = "std")]
+    Io(Error),
+    /// The file doesn't start with the header the format requires.
+    BadHeader {
+        /// Where the header is.
+        at: Location,
+    },
+    /// The file ends partway through an entry.
+    TruncatedEntry {
+        /// Where the incomplete entry starts.
+        at: Location,
+    },
+    /// An LC3Tools memory entry's `.ORIG` flag is something other than 0 or 1.
+    InvalidOrigFlag {
+        /// Where the flag is.
+        at: Location,
+        /// The flag's value.
+        flag: u8,
+    },
+    /// A text format's file isn't valid UTF-8.
+    NotText {
+        /// Where the first invalid byte is.
+        at: Location,
+    },
+    /// A source line isn't valid UTF-8.
+    NonUtf8Line {
+        /// Where the source line starts.
+        at: Location,
+    },
+    /// A word would be placed past the end of the address space.
+    AddressOverflow {
+        /// Where the word is.
+        at: Location,
+    },
+    /// The file isn't a length the format allows.
+    BadLength {
+        /// How long the file is, in bytes.
+        len: u64,
+        /// What the format expects.
+        expected: &'static str,
+    },
+    /// A record (i.e. a line in a text format) couldn't be parsed.
+    BadRecord {
+        /// Where the record is.
+        at: Location,
+        /// What's wrong with it.
+        reason: String,
+    },
+    /// The file isn't well-formed (i.e. isn't valid JSON) or doesn't have
+    /// the fields the format needs.
+    Malformed {
+        /// What's wrong, and where.
+        reason: String,
+    },
+    /// The file doesn't say where its words go.
+    MissingOrigin,
+    /// A byte addressed format only has one of the two bytes of a word.
+    PartialWord {
+        /// The byte address of the byte that is there.
+        byte_addr: u32,
+    },
+}
+
+impl Display for ParseError {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use ParseError::*;
+
+        match self {
+            #[cfg(feature = "std")]
+            Io(err) => write!(fmt, "{}", err),
+            BadHeader { at } => write!(fmt, "{}: unrecognized header", at),
+            TruncatedEntry { at } => write!(fmt, "{}: file ends partway through an entry", at),
+            InvalidOrigFlag { at, flag } => write!(
+                fmt,
+                "{}: invalid .ORIG flag {:#04X} (expected 0 or 1)",
+                at, flag
+            ),
+            NotText { at } => write!(fmt, "{}: file isn't valid UTF-8 text", at),
+            NonUtf8Line { at } => write!(fmt, "{}: source line isn't valid UTF-8", at),
+            AddressOverflow { at } => write!(fmt, "{}: word goes past the end of memory", at),
+            BadLength { len, expected } => {
+                write!(fmt, "file is {} bytes long; expected {}", len, expected)
+            }
+            BadRecord { at, reason } => write!(fmt, "{}: {}", at, reason),
+            Malformed { reason } => write!(fmt, "{}", reason),
+            MissingOrigin => write!(fmt, "missing origin"),
+            PartialWord { byte_addr } => {
+                write!(fmt, "byte address {:#X} is only half of a word", byte_addr)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl error::Error for ParseError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            ParseError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<Error> for ParseError {
+    fn from(err: Error) -> Self {
+        ParseError::Io(err)
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<ParseError> for Error {
+    fn from(err: ParseError) -> Self {
+        match err {
+            ParseError::Io(err) => err,
+            err => Error::new(ErrorKind::InvalidData, err),
+        }
+    }
+}
+
+/// Result type for parsing object files.
+pub type ParseResult<T> = Result<T, ParseError>;
+
+/// Ways writing an object file can fail.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WriteError {
+    /// There's nothing to write and the format can't be empty.
+    Empty,
+    /// The format can only hold a single segment; the object file has to be
+    /// [split](super::SingleSegment::split) first.
+    MultipleSegments,
+    /// The object file couldn't be serialized (i.e. into JSON).
+    Unserializable {
+        /// What went wrong.
+        reason: String,
+    },
+}
+
+impl Display for WriteError {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use WriteError::*;
+
+        match self {
+            Empty => write!(fmt, "Can't write out an empty object file."),
+            MultipleSegments => write!(
+                fmt,
+                "Object file has more than one segment; split it first."
+            ),
+            Unserializable { reason } => write!(fmt, "{}", reason),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl error::Error for WriteError {}
+
+#[cfg(feature = "std")]
+impl From<WriteError> for Error {
+    fn from(err: WriteError) -> Self {
+        let kind = match err {
+            WriteError::Unserializable { .. } => ErrorKind::Other,
+            _ => ErrorKind::InvalidInput,
+        };
+
+        Error::new(kind, err)
+    }
+}
+
+/// Result type for writing object files.
+pub type WriteResult<T> = Result<T, WriteError>;
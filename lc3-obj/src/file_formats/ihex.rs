@@ -0,0 +1,229 @@
+//! [`ObjFileFormat`](super::ObjFileFormat) implementation for
+//! [Intel HEX](https://en.wikipedia.org/wiki/Intel_HEX) files.
+//!
+//! See the [`byte_addressed`](super::byte_addressed) module for how LC-3 words
+//! map onto bytes.
+use super::byte_addressed::{decode_hex, encode_hex, from_bytes, insert_bytes, records, to_bytes};
+use super::{
+    text, Confidence, Loadable, Location, ObjFileFormat, ObjFileWriter, ParseError, ParseResult,
+    WriteResult,
+};
+
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use alloc::{format, vec, vec::Vec};
+use core::fmt::{self, Display};
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+/// Object File type for [`IntelHex`](IntelHex).
+pub struct IntelHexObjFile {
+    loadables: Vec<Loadable>,
+}
+
+impl Display for IntelHexObjFile {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.loadables
+            .iter()
+            .try_for_each(|(addr, word)| writeln!(fmt, "<{:04X}>: {:04X}", addr, word))
+    }
+}
+
+impl From<Vec<Loadable>> for IntelHexObjFile {
+    fn from(loadables: Vec<Loadable>) -> Self {
+        IntelHexObjFile { loadables }
+    }
+}
+
+impl From<IntelHexObjFile> for Vec<Loadable> {
+    fn from(obj: IntelHexObjFile) -> Self {
+        obj.loadables
+    }
+}
+
+/// A single decoded record: type, address, and data.
+type Record = (u8, u16, Vec<u8>);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+/// [`ObjFileFormat`](super::ObjFileFormat) implementation for
+/// [Intel HEX](https://en.wikipedia.org/wiki/Intel_HEX) files.
+///
+/// Words are stored big endian at byte address `2 * addr`; addresses past
+/// `0xFFFF` (i.e. words at `0x8000` and up) use extended linear address
+/// records.
+pub struct IntelHex;
+
+impl IntelHex {
+    const DATA: u8 = 0x00;
+    const END_OF_FILE: u8 = 0x01;
+    const EXTENDED_SEGMENT_ADDR: u8 = 0x02;
+    const START_SEGMENT_ADDR: u8 = 0x03;
+    const EXTENDED_LINEAR_ADDR: u8 = 0x04;
+    const START_LINEAR_ADDR: u8 = 0x05;
+
+    /// Bytes of data per record; this is what most tools use.
+    const RECORD_LEN: usize = 16;
+
+    fn decode_record(line: &str) -> Result<Record, String> {
+        if !line.starts_with(':') {
+            return Err("records must start with a `:`".to_string());
+        }
+
+        let bytes = decode_hex(&line[1..]).ok_or("records must be pairs of hex digits")?;
+        if bytes.len() < 5 || bytes.len() != 5 + bytes[0] as usize {
+            return Err("record length doesn't match its byte count".to_string());
+        }
+
+        if bytes.iter().fold(0u8, |acc, b| acc.wrapping_add(*b)) != 0 {
+            return Err("bad checksum".to_string());
+        }
+
+        let addr = u16::from_be_bytes([bytes[1], bytes[2]]);
+        Ok((bytes[3], addr, bytes[4..bytes.len() - 1].to_vec()))
+    }
+
+    fn write_record(out: &mut String, kind: u8, addr: u16, data: &[u8]) {
+        let [addr_hi, addr_lo] = addr.to_be_bytes();
+
+        let mut record = vec![data.len() as u8, addr_hi, addr_lo, kind];
+        record.extend_from_slice(data);
+        record.push(
+            record
+                .iter()
+                .fold(0u8, |acc, b| acc.wrapping_add(*b))
+                .wrapping_neg(),
+        );
+
+        out.push_str(&format!(":{}\n", encode_hex(&record)));
+    }
+}
+
+impl ObjFileFormat for IntelHex {
+    type Parsed = Vec<Loadable>;
+    type Return = IntelHexObjFile;
+    const NAME: &'static str = "an Intel HEX File";
+
+    fn detect_bytes(bytes: &[u8]) -> Confidence {
+        let text = match text(bytes) {
+            Ok(text) => text,
+            Err(_) => return Confidence::NONE,
+        };
+
+        let mut records = text
+            .lines()
+            .map(str::trim)
+            .filter(|l| !l.is_empty())
+            .map(IntelHex::decode_record);
+
+        match records.next() {
+            Some(Ok(_)) if records.all(|r| r.is_ok()) => Confidence::new(95),
+            _ => Confidence::NONE,
+        }
+    }
+
+    fn parse_bytes(file: &[u8]) -> ParseResult<Self::Return> {
+        let text = text(file)?;
+        let mut bytes = BTreeMap::new();
+        let mut base: u32 = 0;
+
+        for (idx, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let at = Location::Line(idx + 1);
+            let (kind, addr, data) = IntelHex::decode_record(line)
+                .map_err(|reason| ParseError::BadRecord { at, reason })?;
+
+            let base_from = |data: &[u8], shift: u32| match data {
+                [hi, lo] => Ok(u32::from(u16::from_be_bytes([*hi, *lo])) << shift),
+                _ => Err(ParseError::BadRecord {
+                    at,
+                    reason: "malformed address record".to_string(),
+                }),
+            };
+
+            match kind {
+                IntelHex::DATA => insert_bytes(&mut bytes, base + u32::from(addr), &data, at)?,
+                IntelHex::END_OF_FILE => break,
+                IntelHex::EXTENDED_SEGMENT_ADDR => base = base_from(&data, 4)?,
+                IntelHex::EXTENDED_LINEAR_ADDR => base = base_from(&data, 16)?,
+                IntelHex::START_SEGMENT_ADDR | IntelHex::START_LINEAR_ADDR => {}
+                _ => {
+                    return Err(ParseError::BadRecord {
+                        at,
+                        reason: format!("unknown record type {:#04X}", kind),
+                    })
+                }
+            }
+        }
+
+        Ok(from_bytes(&bytes)?.into())
+    }
+}
+
+impl ObjFileWriter for IntelHex {
+    fn from_loadables(loadables: Vec<Loadable>) -> Self::Return {
+        loadables.into()
+    }
+
+    fn write_bytes(obj: &Self::Return) -> WriteResult<Vec<u8>> {
+        let mut out = String::new();
+        let mut upper: u16 = 0;
+
+        for (addr, data) in records(&to_bytes(&obj.loadables), IntelHex::RECORD_LEN) {
+            let [hi, lo, _, _] = addr.to_be_bytes();
+            let addr_upper = u16::from_be_bytes([hi, lo]);
+
+            if addr_upper != upper {
+                upper = addr_upper;
+                IntelHex::write_record(
+                    &mut out,
+                    IntelHex::EXTENDED_LINEAR_ADDR,
+                    0,
+                    &upper.to_be_bytes(),
+                );
+            }
+
+            IntelHex::write_record(&mut out, IntelHex::DATA, addr as u16, &data);
+        }
+
+        IntelHex::write_record(&mut out, IntelHex::END_OF_FILE, 0, &[]);
+
+        Ok(out.into_bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let program = vec![(0x3000, 0x1021), (0x3001, 0xF025), (0x4000, 0x0048)];
+
+        let obj = IntelHex::from_loadables(program.clone());
+        let bytes = IntelHex::write_bytes(&obj).unwrap();
+        let text = core::str::from_utf8(&bytes).unwrap();
+
+        assert_eq!(text.lines().next(), Some(":046000001021F02556"));
+        assert_eq!(text.lines().last(), Some(":00000001FF"));
+        assert_eq!(IntelHex::parse_bytes(&bytes).unwrap(), obj);
+        assert_eq!(Vec::<Loadable>::from(obj), program);
+    }
+
+    #[test]
+    fn high_addresses_use_extended_linear_addresses() {
+        let program = vec![(0x3000, 0x1021), (0x8000, 0xABCD)];
+
+        let obj = IntelHex::from_loadables(program.clone());
+        let bytes = IntelHex::write_bytes(&obj).unwrap();
+        let text = core::str::from_utf8(&bytes).unwrap();
+
+        assert!(text.lines().any(|l| l == ":020000040001F9"));
+        assert_eq!(
+            Vec::<Loadable>::from(IntelHex::parse_bytes(&bytes).unwrap()),
+            program
+        );
+    }
+}
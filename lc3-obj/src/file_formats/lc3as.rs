@@ -0,0 +1,252 @@
+//! [`ObjFileFormat`](super::ObjFileFormat) implementations for the textual
+//! `.hex` and `.bin` files that the classic Patt & Patel `lc3as` assembler
+//! writes out next to its object files.
+//!
+//! Both are one word per line with the origin on the first line; `.hex` files
+//! have words as 4 hex digits and `.bin` files have words as 16 binary digits.
+use super::{
+    only_segment, text, Confidence, Loadable, Location, ObjFileFormat, ObjFileWriter, ParseError,
+    ParseResult, SingleSegment, WriteResult,
+};
+
+use lc3_isa::{Addr, Word};
+
+use alloc::string::String;
+use alloc::{format, vec::Vec};
+use core::fmt::{self, Display};
+use core::iter;
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+/// Object File type for [`Lc3AsHex`](Lc3AsHex) and [`Lc3AsBin`](Lc3AsBin).
+pub struct Lc3AsObjFile {
+    pairs: Vec<Loadable>,
+}
+
+impl Display for Lc3AsObjFile {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.pairs
+            .iter()
+            .try_for_each(|(addr, word)| writeln!(fmt, "<{:04X}>: {:04X}", addr, word))
+    }
+}
+
+impl SingleSegment for Lc3AsObjFile {
+    fn orig(&self) -> Option<Addr> {
+        self.pairs.first().map(|(addr, _)| *addr)
+    }
+}
+
+impl From<Vec<Loadable>> for Lc3AsObjFile {
+    fn from(pairs: Vec<Loadable>) -> Self {
+        Lc3AsObjFile { pairs }
+    }
+}
+
+impl From<Lc3AsObjFile> for Vec<Loadable> {
+    fn from(obj: Lc3AsObjFile) -> Self {
+        obj.pairs
+    }
+}
+
+/// Parses a word that's exactly `digits` digits long in the given radix.
+fn parse_word(line: &str, radix: u32, digits: usize) -> Option<Word> {
+    if line.len() == digits && line.chars().all(|c| c.is_digit(radix)) {
+        Word::from_str_radix(line, radix).ok()
+    } else {
+        None
+    }
+}
+
+fn detect(bytes: &[u8], radix: u32, digits: usize, confidence: Confidence) -> Confidence {
+    let text = match text(bytes) {
+        Ok(text) => text,
+        Err(_) => return Confidence::NONE,
+    };
+
+    let words = text
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty())
+        .map(|l| parse_word(l, radix, digits))
+        .collect::<Option<Vec<_>>>();
+
+    match words {
+        // At least an origin and one word, and they have to fit in memory:
+        Some(ref words)
+            if words.len() >= 2 && u32::from(words[0]) + words.len() as u32 <= 0x1_0001 =>
+        {
+            confidence
+        }
+        _ => Confidence::NONE,
+    }
+}
+
+fn parse(bytes: &[u8], radix: u32, digits: usize) -> ParseResult<Lc3AsObjFile> {
+    let mut words = text(bytes)?
+        .lines()
+        .enumerate()
+        .map(|(idx, l)| (Location::Line(idx + 1), l.trim()))
+        .filter(|(_, l)| !l.is_empty())
+        .map(|(at, l)| {
+            parse_word(l, radix, digits)
+                .map(|word| (at, word))
+                .ok_or_else(|| ParseError::BadRecord {
+                    at,
+                    reason: format!(
+                        "expected a word ({} base {} digits); got `{}`",
+                        digits, radix, l
+                    ),
+                })
+        });
+
+    let (_, orig) = words.next().ok_or(ParseError::MissingOrigin)??;
+
+    let mut pairs = Vec::new();
+    for (idx, word) in words.enumerate() {
+        let (at, word) = word?;
+        let addr = u32::from(orig) + idx as u32;
+
+        if addr > u32::from(Addr::MAX) {
+            return Err(ParseError::AddressOverflow { at });
+        }
+
+        pairs.push((addr as Addr, word));
+    }
+
+    Ok(pairs.into())
+}
+
+fn write(obj: &Lc3AsObjFile, fmt: fn(Word) -> String) -> WriteResult<Vec<u8>> {
+    let (orig, words) = only_segment(obj.pairs.iter().cloned())?;
+
+    let mut text = String::new();
+    iter::once(orig).chain(words).for_each(|word| {
+        text.push_str(&fmt(word));
+        text.push('\n');
+    });
+
+    Ok(text.into_bytes())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+/// [`ObjFileFormat`](super::ObjFileFormat) implementation for `lc3as`'s `.hex`
+/// files: one word per line, as 4 hex digits.
+pub struct Lc3AsHex;
+
+impl Lc3AsHex {
+    const RADIX: u32 = 16;
+    const DIGITS: usize = 4;
+}
+
+impl ObjFileFormat for Lc3AsHex {
+    type Parsed = Vec<Loadable>;
+    type Return = Lc3AsObjFile;
+    const NAME: &'static str = "an lc3as Hex File";
+
+    fn detect_bytes(bytes: &[u8]) -> Confidence {
+        detect(bytes, Self::RADIX, Self::DIGITS, Confidence::new(80))
+    }
+
+    fn parse_bytes(bytes: &[u8]) -> ParseResult<Self::Return> {
+        parse(bytes, Self::RADIX, Self::DIGITS)
+    }
+}
+
+impl ObjFileWriter for Lc3AsHex {
+    fn from_loadables(loadables: Vec<Loadable>) -> Self::Return {
+        loadables.into()
+    }
+
+    fn write_bytes(obj: &Self::Return) -> WriteResult<Vec<u8>> {
+        write(obj, |w| format!("{:04X}", w))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+/// [`ObjFileFormat`](super::ObjFileFormat) implementation for `lc3as`'s `.bin`
+/// files: one word per line, as 16 binary digits.
+pub struct Lc3AsBin;
+
+impl Lc3AsBin {
+    const RADIX: u32 = 2;
+    const DIGITS: usize = 16;
+}
+
+impl ObjFileFormat for Lc3AsBin {
+    type Parsed = Vec<Loadable>;
+    type Return = Lc3AsObjFile;
+    const NAME: &'static str = "an lc3as Binary Text File";
+
+    fn detect_bytes(bytes: &[u8]) -> Confidence {
+        detect(bytes, Self::RADIX, Self::DIGITS, Confidence::new(90))
+    }
+
+    fn parse_bytes(bytes: &[u8]) -> ParseResult<Self::Return> {
+        parse(bytes, Self::RADIX, Self::DIGITS)
+    }
+}
+
+impl ObjFileWriter for Lc3AsBin {
+    fn from_loadables(loadables: Vec<Loadable>) -> Self::Return {
+        loadables.into()
+    }
+
+    fn write_bytes(obj: &Self::Return) -> WriteResult<Vec<u8>> {
+        write(obj, |w| format!("{:016b}", w))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_formats::WriteError;
+
+    use alloc::vec;
+
+    fn program() -> Vec<Loadable> {
+        vec![(0x3000, 0x1021), (0x3001, 0xF025)]
+    }
+
+    #[test]
+    fn hex_round_trip() {
+        let obj = Lc3AsHex::from_loadables(program());
+        let bytes = Lc3AsHex::write_bytes(&obj).unwrap();
+
+        assert_eq!(bytes, b"3000\n1021\nF025\n");
+        assert_eq!(Lc3AsHex::parse_bytes(&bytes).unwrap(), obj);
+        assert_eq!(Vec::<Loadable>::from(obj), program());
+    }
+
+    #[test]
+    fn bin_round_trip() {
+        let obj = Lc3AsBin::from_loadables(program());
+        let bytes = Lc3AsBin::write_bytes(&obj).unwrap();
+
+        assert_eq!(
+            bytes,
+            &b"0011000000000000\n0001000000100001\n1111000000100101\n"[..]
+        );
+        assert_eq!(Lc3AsBin::parse_bytes(&bytes).unwrap(), obj);
+        assert!(Lc3AsHex::parse_bytes(&bytes).is_err());
+    }
+
+    #[test]
+    fn one_segment_per_file() {
+        let obj = Lc3AsHex::from_loadables(vec![(0x3000, 0x1021), (0x4000, 0xF025)]);
+
+        assert_eq!(
+            Lc3AsHex::write_bytes(&obj),
+            Err(WriteError::MultipleSegments)
+        );
+    }
+
+    #[test]
+    fn words_past_the_end_of_memory() {
+        assert!(matches!(
+            Lc3AsHex::parse_bytes(b"FFFF\n1021\nF025\n"),
+            Err(ParseError::AddressOverflow {
+                at: Location::Line(3)
+            })
+        ));
+    }
+}
@@ -0,0 +1,448 @@
+//! [`ObjFileFormat`](super::ObjFileFormat) implementation for
+//! [LC3Tools](https://github.com/chiragsakhuja/lc3tools).
+use super::{
+    segments, Confidence, Loadable, Location, ObjFileFormat, ObjFileWriter, ParseError,
+    ParseResult, SourceLine, WriteResult,
+};
+use lc3_isa::{Addr, Word};
+
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use alloc::{format, vec::Vec};
+use core::fmt::{self, Display};
+use core::iter;
+use core::marker::PhantomData;
+use core::str;
+
+use byteorder::{ByteOrder, LittleEndian};
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+/// A single memory entry in an [`Lc3ToolsObjFile`](Lc3ToolsObjFile).
+pub struct MemEntry {
+    word: Word,
+    orig: bool,
+    line: String,
+}
+
+impl MemEntry {
+    fn new(word: Word, orig: bool, line: String) -> Self {
+        Self { word, orig, line }
+    }
+}
+
+impl Display for MemEntry {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.orig {
+            write!(fmt, "<orig: {:04X}>", self.word)?
+        } else {
+            write!(fmt, "<{:04X}>", self.word)?
+        }
+
+        writeln!(fmt, "  {}", self.line)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+/// Object File type for [`Lc3Tools`](Lc3Tools).
+pub struct Lc3ToolsObjFile<'a> {
+    version: [u8; 2],
+    memory_entries: Vec<MemEntry>,
+    warnings: Vec<String>,
+    // pos: Option<Addr>,
+    _p: PhantomData<&'a ()>,
+}
+
+impl Lc3ToolsObjFile<'_> {
+    /// Returns the version of the encoding used for the object file that was
+    /// parsed.
+    pub fn get_version(&self) -> u16 {
+        LittleEndian::read_u16(&self.version)
+    }
+
+    /// Returns anything worth warning about that came up while parsing the
+    /// object file (i.e. an untested version, or skipped bytes).
+    pub fn warnings(&self) -> &[String] {
+        &self.warnings
+    }
+
+    /// Makes an object file out of a set of loadables, attaching the given
+    /// source lines to the words they belong to.
+    ///
+    /// `.ORIG` entries are reconstructed from contiguous runs of loadables and
+    /// get synthesized `.ORIG` lines; words without a source line get empty
+    /// lines.
+    pub fn with_source_lines(loadables: Vec<Loadable>, lines: &BTreeMap<Addr, SourceLine>) -> Self {
+        let memory_entries = segments(loadables)
+            .into_iter()
+            .flat_map(|(orig, words)| {
+                iter::once(MemEntry::new(orig, true, format!(".ORIG x{:04X}", orig))).chain(
+                    words.into_iter().enumerate().map(move |(idx, word)| {
+                        let addr = orig.wrapping_add(idx as Addr);
+                        let line = lines.get(&addr).map(|l| l.text.clone()).unwrap_or_default();
+
+                        MemEntry::new(word, false, line)
+                    }),
+                )
+            })
+            .collect();
+
+        Lc3ToolsObjFile {
+            version: Lc3Tools::TESTED_VERSION,
+            memory_entries,
+            warnings: Vec::new(),
+            _p: PhantomData,
+        }
+    }
+
+    /// Returns the source line of every word in the object file, keyed on the
+    /// word's address.
+    pub fn source_lines(&self) -> BTreeMap<Addr, SourceLine> {
+        self.placed_entries()
+            .map(|(addr, idx, m)| {
+                let line = SourceLine {
+                    text: m.line.clone(),
+                    ordinal: idx + 1,
+                };
+
+                (addr, line)
+            })
+            .collect()
+    }
+
+    /// Pairs every (non `.ORIG`) memory entry with its address and its index
+    /// in the file.
+    fn placed_entries(&self) -> impl Iterator<Item = (Addr, usize, &MemEntry)> + '_ {
+        let mut addr = 0x0000;
+        self.memory_entries
+            .iter()
+            .enumerate()
+            .filter_map(move |(idx, m)| {
+                if m.orig {
+                    addr = m.word;
+                    None
+                } else {
+                    // Parsing makes sure segments don't run past the end of memory
+                    // so this only wraps after the last entry in a segment:
+                    let a = addr;
+                    addr = addr.wrapping_add(1);
+
+                    Some((a, idx, m))
+                }
+            })
+    }
+}
+
+impl Display for Lc3ToolsObjFile<'_> {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.memory_entries
+            .iter()
+            .try_for_each(|m| Display::fmt(m, fmt))
+    }
+}
+
+impl From<Lc3ToolsObjFile<'_>> for Vec<Loadable> {
+    fn from(obj: Lc3ToolsObjFile<'_>) -> Self {
+        obj.placed_entries().map(|(a, _, m)| (a, m.word)).collect()
+    }
+}
+
+impl From<Vec<Loadable>> for Lc3ToolsObjFile<'_> {
+    /// Reconstructs `.ORIG` entries from contiguous runs of loadables.
+    ///
+    /// Since there's no source to go off of, every `.ORIG` entry gets a
+    /// synthesized `.ORIG` line and all other entries get empty lines.
+    fn from(loadables: Vec<Loadable>) -> Self {
+        Self::with_source_lines(loadables, &BTreeMap::new())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+/// [`ObjFileFormat`](super::ObjFileFormat) implementation for
+/// [LC3Tools](https://github.com/chiragsakhuja/lc3tools).
+pub struct Lc3Tools<'a>(PhantomData<&'a ()>);
+
+impl Lc3Tools<'_> {
+    const HEADER: [u8; 5] = [0x1c, 0x30, 0x15, 0xc0, 0x01];
+    const TESTED_VERSION: [u8; 2] = [0x01, 0x01];
+}
+
+impl Lc3Tools<'_> {
+    /// Reads the memory entry that starts at `start`, returning it and where
+    /// the next one starts.
+    fn read_mem_entry(bytes: &[u8], start: usize) -> ParseResult<(MemEntry, usize)> {
+        let truncated = || ParseError::TruncatedEntry {
+            at: Location::Byte(start as u64),
+        };
+        let field = |from: usize, len: usize| bytes.get(from..from + len).ok_or_else(truncated);
+
+        let word = LittleEndian::read_u16(field(start, 2)?);
+
+        let orig = match field(start + 2, 1)?[0] {
+            0 => false,
+            1 => true,
+            flag => {
+                return Err(ParseError::InvalidOrigFlag {
+                    at: Location::Byte(start as u64 + 2),
+                    flag,
+                })
+            }
+        };
+
+        let str_len = LittleEndian::read_u32(field(start + 3, 4)?) as usize;
+        let line_start = start + 7;
+
+        // Check the length before slicing so huge lengths don't overflow:
+        if bytes.len() - line_start < str_len {
+            return Err(truncated());
+        }
+
+        let line =
+            str::from_utf8(field(line_start, str_len)?).map_err(|_| ParseError::NonUtf8Line {
+                at: Location::Byte(line_start as u64),
+            })?;
+
+        Ok((
+            MemEntry::new(word, orig, line.to_string()),
+            line_start + str_len,
+        ))
+    }
+
+    fn write_mem_entry(bytes: &mut Vec<u8>, mem_entry: &MemEntry) {
+        bytes.extend_from_slice(&mem_entry.word.to_le_bytes());
+        bytes.push(u8::from(mem_entry.orig));
+
+        bytes.extend_from_slice(&(mem_entry.line.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(mem_entry.line.as_bytes());
+    }
+}
+
+impl<'a> ObjFileFormat for Lc3Tools<'a> {
+    type Parsed = Vec<Loadable>;
+    type Return = Lc3ToolsObjFile<'a>;
+    const NAME: &'static str = "an LC3Tools Object File";
+
+    fn detect_bytes(bytes: &[u8]) -> Confidence {
+        if bytes.get(..5) != Some(&Lc3Tools::HEADER[..]) {
+            return Confidence::NONE;
+        }
+
+        // The header is a pretty good sign; an unfamiliar version is still
+        // probably fine.
+        match bytes.get(5..7) {
+            Some(version) if version == Lc3Tools::TESTED_VERSION => Confidence::CERTAIN,
+            _ => Confidence::new(90),
+        }
+    }
+
+    fn parse_bytes(bytes: &[u8]) -> ParseResult<Self::Return> {
+        Lc3Tools::parse_entries(bytes, false)
+    }
+
+    /// Stops at the first memory entry that can't be read (i.e. a half written
+    /// entry at the end of the file) and skips everything after it.
+    fn parse_bytes_lenient(bytes: &[u8]) -> ParseResult<Self::Return> {
+        Lc3Tools::parse_entries(bytes, true)
+    }
+
+    fn warnings(obj: &Self::Return) -> Vec<String> {
+        obj.warnings.clone()
+    }
+}
+
+impl Lc3Tools<'_> {
+    /// Reads the version of the encoding used for an object file out of its
+    /// header, without parsing the rest of the file.
+    pub fn version(bytes: &[u8]) -> Option<u16> {
+        match bytes.get(..7) {
+            Some(header) if header[..5] == Lc3Tools::HEADER => {
+                Some(LittleEndian::read_u16(&header[5..]))
+            }
+            _ => None,
+        }
+    }
+
+    fn parse_entries<'a>(bytes: &[u8], lenient: bool) -> ParseResult<Lc3ToolsObjFile<'a>> {
+        if bytes.get(..5) != Some(&Lc3Tools::HEADER[..]) {
+            return Err(ParseError::BadHeader {
+                at: Location::Byte(0),
+            });
+        }
+
+        let mut version: [u8; 2] = [0; 2];
+        version.copy_from_slice(bytes.get(5..7).ok_or(ParseError::BadHeader {
+            at: Location::Byte(0),
+        })?);
+
+        let mut warnings = Vec::new();
+        if version != Lc3Tools::TESTED_VERSION {
+            warnings.push(format!(
+                "untested object file version ({:#06X}; tested with {:#06X})",
+                LittleEndian::read_u16(&version),
+                LittleEndian::read_u16(&Lc3Tools::TESTED_VERSION)
+            ));
+        }
+
+        let mut memory_entries = Vec::<MemEntry>::new();
+        let mut addr: u32 = 0x0000;
+        let mut start = 7;
+
+        while start < bytes.len() {
+            match Lc3Tools::read_mem_entry(bytes, start) {
+                Ok((mem_entry, next)) => {
+                    // Segments can't run past the end of memory:
+                    if mem_entry.orig {
+                        addr = u32::from(mem_entry.word);
+                    } else if addr > u32::from(Addr::MAX) {
+                        return Err(ParseError::AddressOverflow {
+                            at: Location::Byte(start as u64),
+                        });
+                    } else {
+                        addr += 1;
+                    }
+
+                    memory_entries.push(mem_entry);
+                    start = next;
+                }
+                Err(err) if lenient => {
+                    warnings.push(format!(
+                        "ignoring the last {} bytes (from byte {:#X} on); {}",
+                        bytes.len() - start,
+                        start,
+                        err
+                    ));
+                    break;
+                }
+                Err(err) => return Err(err),
+            }
+        }
+
+        Ok(Lc3ToolsObjFile {
+            version,
+            memory_entries,
+            warnings,
+            _p: PhantomData,
+        })
+    }
+}
+
+impl<'a> ObjFileWriter for Lc3Tools<'a> {
+    fn from_loadables(loadables: Vec<Loadable>) -> Self::Return {
+        loadables.into()
+    }
+
+    fn write_bytes(obj: &Self::Return) -> WriteResult<Vec<u8>> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&Lc3Tools::HEADER);
+        bytes.extend_from_slice(&obj.version);
+
+        obj.memory_entries
+            .iter()
+            .for_each(|m| Lc3Tools::write_mem_entry(&mut bytes, m));
+
+        Ok(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use alloc::vec;
+
+    fn program() -> Vec<Loadable> {
+        vec![
+            (0x3000, 0x1021),
+            (0x3001, 0xF025),
+            (0x4000, 0x0048),
+            (0x4001, 0x0000),
+        ]
+    }
+
+    #[test]
+    fn round_trip() {
+        let obj = Lc3Tools::from_loadables(program());
+        let parsed = Lc3Tools::parse_bytes(&Lc3Tools::write_bytes(&obj).unwrap()).unwrap();
+
+        assert_eq!(parsed, obj);
+        assert_eq!(parsed.get_version(), 0x0101);
+        assert!(parsed.warnings().is_empty());
+        assert_eq!(Vec::<Loadable>::from(parsed), program());
+    }
+
+    #[test]
+    fn round_trip_keeps_source_lines() {
+        let line = SourceLine {
+            text: "ADD R0, R0, #1".to_string(),
+            ordinal: 2,
+        };
+        let lines = iter::once((0x3000, line)).collect();
+
+        let obj = Lc3ToolsObjFile::with_source_lines(program(), &lines);
+        let parsed = Lc3Tools::parse_bytes(&Lc3Tools::write_bytes(&obj).unwrap()).unwrap();
+        let parsed_lines = parsed.source_lines();
+
+        assert_eq!(parsed_lines[&0x3000].text, "ADD R0, R0, #1");
+        assert_eq!(parsed_lines[&0x3001].text, "");
+        assert_eq!(parsed_lines.len(), program().len());
+    }
+
+    #[test]
+    fn written_files_are_detected() {
+        let bytes = Lc3Tools::write_bytes(&Lc3Tools::from_loadables(program())).unwrap();
+
+        assert_eq!(Lc3Tools::detect_bytes(&bytes), Confidence::CERTAIN);
+        assert_eq!(Lc3Tools::version(&bytes), Some(0x0101));
+    }
+
+    #[test]
+    fn truncated_files() {
+        let bytes = Lc3Tools::write_bytes(&Lc3Tools::from_loadables(program())).unwrap();
+        let truncated = &bytes[..bytes.len() - 3];
+
+        // The last entry (a word with an empty line) is 7 bytes long:
+        let last = bytes.len() as u64 - 7;
+        assert!(matches!(
+            Lc3Tools::parse_bytes(truncated),
+            Err(ParseError::TruncatedEntry { at: Location::Byte(at) }) if at == last
+        ));
+
+        let parsed = Lc3Tools::parse_bytes_lenient(truncated).unwrap();
+        assert_eq!(Lc3Tools::warnings(&parsed).len(), 1);
+        assert_eq!(Vec::<Loadable>::from(parsed), &program()[..3]);
+    }
+
+    #[test]
+    fn untested_versions_are_warned_about() {
+        let mut bytes = Lc3Tools::write_bytes(&Lc3Tools::from_loadables(program())).unwrap();
+        bytes[6] = 0x02;
+
+        let parsed = Lc3Tools::parse_bytes(&bytes).unwrap();
+        assert_eq!(parsed.get_version(), 0x0201);
+        assert_eq!(
+            parsed.warnings(),
+            ["untested object file version (0x0201; tested with 0x0101)"]
+        );
+        assert_eq!(Vec::<Loadable>::from(parsed), program());
+    }
+
+    #[test]
+    fn trailing_garbage() {
+        let mut bytes = Lc3Tools::write_bytes(&Lc3Tools::from_loadables(program())).unwrap();
+        let flag = bytes.len() as u64 + 2;
+        bytes.extend_from_slice(&[0x00, 0x30, 0x05]);
+
+        assert!(matches!(
+            Lc3Tools::parse_bytes(&bytes),
+            Err(ParseError::InvalidOrigFlag { at: Location::Byte(at), flag: 0x05 }) if at == flag
+        ));
+
+        let parsed = Lc3Tools::parse_bytes_lenient(&bytes).unwrap();
+        assert_eq!(Vec::<Loadable>::from(parsed), program());
+
+        let _ = bytes.pop();
+        assert!(matches!(
+            Lc3Tools::parse_bytes(&bytes),
+            Err(ParseError::TruncatedEntry { .. })
+        ));
+    }
+}
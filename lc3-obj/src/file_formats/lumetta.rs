@@ -0,0 +1,233 @@
+//! [`ObjFileFormat`](super::ObjFileFormat) implementation for the file format
+//! used by [Steven S. Lumetta's assembler and simulator]
+//! (http://highered.mheducation.com/sites/0072467509/student_view0/lc-3_simulator.html).
+use super::{
+    only_segment, Confidence, Loadable, Location, ObjFileFormat, ObjFileWriter, ParseError,
+    ParseResult, SingleSegment, WriteResult,
+};
+
+use lc3_isa::{Addr, Instruction, Word};
+
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+use core::fmt::{self, Display};
+use core::marker::PhantomData;
+
+use byteorder::{BigEndian, ByteOrder};
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+/// Object File type for [`Lumetta`](Lumetta).
+pub struct LumettaObjFile<'a> {
+    pairs: Vec<Loadable>,
+    _p: PhantomData<&'a ()>,
+}
+
+impl Display for LumettaObjFile<'_> {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.pairs
+            .iter()
+            .try_for_each(|(addr, word)| writeln!(fmt, "<{:04X}>: {:04X}", addr, word))
+    }
+}
+
+impl SingleSegment for LumettaObjFile<'_> {
+    fn orig(&self) -> Option<Addr> {
+        self.pairs.first().map(|(addr, _)| *addr)
+    }
+}
+
+impl From<Vec<Loadable>> for LumettaObjFile<'_> {
+    fn from(pairs: Vec<Loadable>) -> Self {
+        LumettaObjFile {
+            pairs,
+            _p: PhantomData,
+        }
+    }
+}
+
+impl From<LumettaObjFile<'_>> for Vec<Loadable> {
+    fn from(obj: LumettaObjFile<'_>) -> Self {
+        obj.pairs
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+/// [`ObjFileFormat`](super::ObjFileFormat) implementation for the file format
+/// used by [Steven S. Lumetta's assembler and simulator]
+/// (http://highered.mheducation.com/sites/0072467509/student_view0/lc-3_simulator.html).
+pub struct Lumetta;
+
+impl Lumetta {
+    fn has_valid_length(bytes: &[u8]) -> bool {
+        // At least 1 instruction and an even number of bytes:
+        bytes.len() >= 4 && bytes.len().is_multiple_of(2)
+    }
+
+    /// Whether the file is text (possibly padded out with NULs) rather than a
+    /// program; text with an even number of bytes is otherwise the right
+    /// shape.
+    fn looks_like_text(bytes: &[u8]) -> bool {
+        let end = bytes.iter().rposition(|b| *b != 0).map_or(0, |i| i + 1);
+
+        end > 0
+            && bytes[..end]
+                .iter()
+                .all(|b| b.is_ascii_graphic() || b.is_ascii_whitespace())
+    }
+
+    /// Whether most of the file is NULs at the end, as in files that were
+    /// padded out to a block size.
+    fn is_mostly_padding(bytes: &[u8]) -> bool {
+        bytes.iter().rev().take_while(|b| **b == 0).count() * 2 > bytes.len()
+    }
+}
+
+impl<'a> ObjFileFormat for &'a Lumetta {
+    type Parsed = Vec<Loadable>;
+    type Return = LumettaObjFile<'a>;
+    const NAME: &'static str = "an Object File for Steven S. Lumetta's simulator and assembler";
+
+    /// The format has no header or magic number so this is mostly guesswork:
+    /// files that are the right shape get a low score that goes up if the
+    /// words decode as instructions and the origin is in user space, and goes
+    /// down if the file looks like text or padding.
+    fn detect_bytes(bytes: &[u8]) -> Confidence {
+        if !Lumetta::has_valid_length(bytes) {
+            return Confidence::NONE;
+        }
+
+        let orig = BigEndian::read_u16(&bytes[0..2]);
+        let words: Vec<Word> = bytes[2..].chunks(2).map(BigEndian::read_u16).collect();
+
+        // The words have to fit between the origin and the end of memory:
+        if u32::from(orig) + words.len() as u32 > 0x1_0000 {
+            return Confidence::NONE;
+        }
+
+        let decodable = words
+            .iter()
+            .filter(|w| Instruction::try_from(**w).is_ok())
+            .count();
+        let decodable = (decodable * 40 / words.len()) as u8;
+        let user_space = if (0x3000..0xFE00).contains(&orig) {
+            10
+        } else {
+            0
+        };
+
+        let penalty = if Lumetta::looks_like_text(bytes) {
+            40
+        } else if Lumetta::is_mostly_padding(bytes) {
+            20
+        } else {
+            0
+        };
+
+        Confidence::new((10 + decodable + user_space).saturating_sub(penalty))
+    }
+
+    fn parse_bytes(bytes: &[u8]) -> ParseResult<Self::Return> {
+        if !Lumetta::has_valid_length(bytes) {
+            return Err(ParseError::BadLength {
+                len: bytes.len() as u64,
+                expected: "an even number of bytes, at least 4",
+            });
+        }
+
+        let orig: Addr = BigEndian::read_u16(&bytes[0..2]);
+        let mut pairs: Vec<Loadable> = Vec::with_capacity((bytes.len() / 2) - 1);
+
+        for (idx, word) in bytes[2..].chunks(2).map(BigEndian::read_u16).enumerate() {
+            let addr = u32::from(orig) + idx as u32;
+
+            if addr > u32::from(Addr::MAX) {
+                return Err(ParseError::AddressOverflow {
+                    at: Location::Byte(2 + 2 * idx as u64),
+                });
+            }
+
+            pairs.push((addr as Addr, word));
+        }
+
+        Ok(LumettaObjFile {
+            pairs,
+            _p: PhantomData,
+        })
+    }
+}
+
+impl ObjFileWriter for &Lumetta {
+    fn from_loadables(loadables: Vec<Loadable>) -> Self::Return {
+        loadables.into()
+    }
+
+    fn write_bytes(obj: &Self::Return) -> WriteResult<Vec<u8>> {
+        let (orig, words) = only_segment(obj.pairs.iter().cloned())?;
+
+        let mut bytes = Vec::with_capacity(2 * (words.len() + 1));
+        bytes.extend_from_slice(&orig.to_be_bytes());
+        words
+            .into_iter()
+            .for_each(|word| bytes.extend_from_slice(&word.to_be_bytes()));
+
+        Ok(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_formats::WriteError;
+
+    use alloc::vec;
+
+    #[test]
+    fn round_trip() {
+        let program = vec![(0x3000, 0x1021), (0x3001, 0x0048), (0x3002, 0xF025)];
+
+        let obj = <&Lumetta>::from_loadables(program.clone());
+        let bytes = <&Lumetta>::write_bytes(&obj).unwrap();
+
+        assert_eq!(bytes, [0x30, 0x00, 0x10, 0x21, 0x00, 0x48, 0xF0, 0x25]);
+        assert_eq!(<&Lumetta>::parse_bytes(&bytes).unwrap(), obj);
+        assert_eq!(Vec::<Loadable>::from(obj), program);
+    }
+
+    #[test]
+    fn segments_have_to_be_split_first() {
+        let obj = <&Lumetta>::from_loadables(vec![(0x3000, 0x1021), (0x4000, 0xF025)]);
+
+        assert_eq!(
+            <&Lumetta>::write_bytes(&obj),
+            Err(WriteError::MultipleSegments)
+        );
+
+        let split = obj.split();
+        assert_eq!(split.len(), 2);
+        assert_eq!(split[1].orig(), Some(0x4000));
+        assert_eq!(
+            <&Lumetta>::write_bytes(&split[1]).unwrap(),
+            [0x40, 0x00, 0xF0, 0x25]
+        );
+    }
+
+    #[test]
+    fn detection() {
+        let program = [0x30, 0x00, 0x12, 0x61, 0x10, 0x21, 0xF0, 0x25];
+        let score = <&Lumetta>::detect_bytes(&program).percent();
+
+        assert!(score >= 50);
+        assert!(<&Lumetta>::detect_bytes(b"hello world!").percent() < 50);
+        assert!(<&Lumetta>::detect_bytes(b"hello world!\0\0\0\0").percent() < 50);
+
+        let mut padded = program.to_vec();
+        padded.resize(64, 0);
+        assert!(<&Lumetta>::detect_bytes(&padded).percent() < score);
+    }
+
+    #[test]
+    fn bad_lengths() {
+        assert!(<&Lumetta>::parse_bytes(&[0x30, 0x00]).is_err());
+        assert!(<&Lumetta>::parse_bytes(&[0x30, 0x00, 0x10]).is_err());
+    }
+}
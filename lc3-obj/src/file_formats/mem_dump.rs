@@ -0,0 +1,156 @@
+//! [`ObjFileFormat`](super::ObjFileFormat) implementation for full memory
+//! images, as written by
+//! [`FileBackedMemoryShim`](lc3_shims::memory::FileBackedMemoryShim).
+use super::{Confidence, Loadable, ObjFileFormat, ParseError, ParseResult};
+#[cfg(feature = "std")]
+use super::{ImageWriter, IoResult};
+
+#[cfg(feature = "std")]
+use lc3_isa::util::MemoryDump;
+use lc3_isa::{Addr, Word, ADDR_SPACE_SIZE_IN_WORDS};
+
+use alloc::{vec, vec::Vec};
+use core::fmt::{self, Display};
+use core::ops::RangeInclusive;
+
+#[cfg(feature = "std")]
+use std::io::Write;
+
+use byteorder::{ByteOrder, LittleEndian};
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+/// Object File type for [`MemDump`](MemDump).
+pub struct MemDumpObjFile {
+    memory: Vec<Word>,
+}
+
+impl MemDumpObjFile {
+    /// Pulls the given region out of the memory dump.
+    ///
+    /// Since there's no way to tell unused memory apart from memory that was
+    /// explicitly zeroed, zeros at the start and end of the region are
+    /// dropped. Zeros in between are kept, so programs that reserve zeroed
+    /// space stay in one piece.
+    pub fn extract(&self, region: RangeInclusive<Addr>) -> Vec<Loadable> {
+        let used = |addr: &Addr| self.memory[*addr as usize] != 0;
+
+        match (region.clone().find(used), region.rev().find(used)) {
+            (Some(start), Some(end)) => (start..=end)
+                .map(|addr| (addr, self.memory[addr as usize]))
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+impl Display for MemDumpObjFile {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.extract(0x0000..=0xFFFF)
+            .iter()
+            .try_for_each(|(addr, word)| writeln!(fmt, "<{:04X}>: {:04X}", addr, word))
+    }
+}
+
+impl From<MemDumpObjFile> for Vec<Loadable> {
+    fn from(obj: MemDumpObjFile) -> Self {
+        obj.extract(0x0000..=0xFFFF)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+/// [`ObjFileFormat`](super::ObjFileFormat) implementation for full memory
+/// images.
+///
+/// These are just every word in the address space, in order, as little endian
+/// `u16`s.
+pub struct MemDump;
+
+impl ObjFileFormat for MemDump {
+    type Parsed = Vec<Loadable>;
+    type Return = MemDumpObjFile;
+    const NAME: &'static str = "a Memory Dump";
+
+    fn detect_bytes(bytes: &[u8]) -> Confidence {
+        // Any file that's exactly the right size could be a memory dump;
+        // there isn't anything else to go off of.
+        if bytes.len() == ADDR_SPACE_SIZE_IN_WORDS * 2 {
+            Confidence::new(70)
+        } else {
+            Confidence::NONE
+        }
+    }
+
+    fn parse_bytes(bytes: &[u8]) -> ParseResult<Self::Return> {
+        if !Self::detect_bytes(bytes).is_match() {
+            return Err(ParseError::BadLength {
+                len: bytes.len() as u64,
+                expected: "exactly 128KiB (65536 words)",
+            });
+        }
+
+        let mut memory = vec![0; ADDR_SPACE_SIZE_IN_WORDS];
+        LittleEndian::read_u16_into(bytes, &mut memory);
+
+        Ok(MemDumpObjFile { memory })
+    }
+}
+
+#[cfg(feature = "std")]
+impl ImageWriter for MemDump {
+    const NAME: &'static str = "a Memory Dump";
+
+    fn write<W: Write>(&self, image: &MemoryDump, file: &mut W) -> IoResult<()> {
+        let mut bytes = vec![0; ADDR_SPACE_SIZE_IN_WORDS * 2];
+        LittleEndian::write_u16_into(&image[..], &mut bytes);
+
+        file.write_all(&bytes)?;
+        file.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dump(words: &[Loadable]) -> MemDumpObjFile {
+        let mut memory = vec![0; ADDR_SPACE_SIZE_IN_WORDS];
+        for (addr, word) in words {
+            memory[*addr as usize] = *word;
+        }
+
+        MemDumpObjFile { memory }
+    }
+
+    #[test]
+    fn zeros_inside_the_region_are_kept() {
+        let obj = dump(&[(0x3000, 0x1021), (0x3100, 0xF025)]);
+        let loadables = obj.extract(0x3000..=0xFDFF);
+
+        assert_eq!(loadables.len(), 0x101);
+        assert_eq!(loadables.first(), Some(&(0x3000, 0x1021)));
+        assert_eq!(loadables[0x80], (0x3080, 0));
+        assert_eq!(loadables.last(), Some(&(0x3100, 0xF025)));
+    }
+
+    #[test]
+    fn zeros_at_the_ends_are_trimmed() {
+        let obj = dump(&[(0x0200, 0x1234), (0x3005, 0x1021), (0x3006, 0xF025)]);
+
+        assert_eq!(
+            obj.extract(0x3000..=0xFDFF),
+            vec![(0x3005, 0x1021), (0x3006, 0xF025)]
+        );
+        assert_eq!(obj.extract(0x4000..=0x4FFF), vec![]);
+    }
+
+    #[test]
+    fn round_trip() {
+        let obj = dump(&[(0x3000, 0x1021), (0xFFFF, 0xABCD)]);
+
+        let mut bytes = vec![0; ADDR_SPACE_SIZE_IN_WORDS * 2];
+        LittleEndian::write_u16_into(&obj.memory, &mut bytes);
+
+        assert_eq!(MemDump::parse_bytes(&bytes).unwrap(), obj);
+        assert!(MemDump::parse_bytes(&bytes[1..]).is_err());
+    }
+}
@@ -0,0 +1,153 @@
+//! [`ImageWriter`](super::ImageWriter) implementations for the memory
+//! initialization files HDL tools use to initialize block RAMs: Verilog's
+//! `$readmemh`, Xilinx's `.coe` files, and Intel (Altera)'s `.mif` files.
+//!
+//! All of these describe the full 64K word address space with 16-bit words.
+use super::{runs, ImageWriter, IoResult};
+
+use lc3_isa::util::MemoryDump;
+use lc3_isa::ADDR_SPACE_SIZE_IN_WORDS;
+
+use std::io::Write;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+/// [`ImageWriter`](super::ImageWriter) implementation for files that can be
+/// loaded with Verilog's `$readmemh`: one word per line, as 4 hex digits.
+///
+/// Every word is written out (rather than using `@` address directives to skip
+/// over empty memory) so that no part of the memory is left uninitialized.
+pub struct ReadMemH;
+
+impl ImageWriter for ReadMemH {
+    const NAME: &'static str = "a Verilog $readmemh File";
+
+    fn write<W: Write>(&self, image: &MemoryDump, file: &mut W) -> IoResult<()> {
+        writeln!(
+            file,
+            "// {} x 16-bit words, for $readmemh",
+            ADDR_SPACE_SIZE_IN_WORDS
+        )?;
+
+        image
+            .iter()
+            .try_for_each(|word| writeln!(file, "{:04X}", word))?;
+
+        file.flush()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+/// [`ImageWriter`](super::ImageWriter) implementation for Xilinx's coefficient
+/// (`.coe`) files.
+pub struct Coe;
+
+impl ImageWriter for Coe {
+    const NAME: &'static str = "a Xilinx Coefficient File";
+
+    fn write<W: Write>(&self, image: &MemoryDump, file: &mut W) -> IoResult<()> {
+        writeln!(file, "; {} x 16-bit words", ADDR_SPACE_SIZE_IN_WORDS)?;
+        writeln!(file, "memory_initialization_radix=16;")?;
+        writeln!(file, "memory_initialization_vector=")?;
+
+        let last = image.len() - 1;
+        image.iter().enumerate().try_for_each(|(addr, word)| {
+            let sep = if addr == last { ';' } else { ',' };
+            writeln!(file, "{:04X}{}", word, sep)
+        })?;
+
+        file.flush()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+/// [`ImageWriter`](super::ImageWriter) implementation for Intel (Altera)'s
+/// memory initialization (`.mif`) files.
+///
+/// Runs of repeated words are written as address ranges to keep the mostly
+/// empty address space from taking up 64K lines.
+pub struct Mif;
+
+impl ImageWriter for Mif {
+    const NAME: &'static str = "an Intel Memory Initialization File";
+
+    fn write<W: Write>(&self, image: &MemoryDump, file: &mut W) -> IoResult<()> {
+        writeln!(file, "WIDTH=16;")?;
+        writeln!(file, "DEPTH={};", ADDR_SPACE_SIZE_IN_WORDS)?;
+        writeln!(file)?;
+        writeln!(file, "ADDRESS_RADIX=HEX;")?;
+        writeln!(file, "DATA_RADIX=HEX;")?;
+        writeln!(file)?;
+        writeln!(file, "CONTENT BEGIN")?;
+
+        runs(image)
+            .into_iter()
+            .try_for_each(|(start, len, word)| match len {
+                1 => writeln!(file, "\t{:04X} : {:04X};", start, word),
+                _ => writeln!(
+                    file,
+                    "\t[{:04X}..{:04X}] : {:04X};",
+                    start,
+                    start + len - 1,
+                    word
+                ),
+            })?;
+
+        writeln!(file, "END;")?;
+        file.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn written<I: ImageWriter>(format: I) -> String {
+        let mut image = MemoryDump::blank();
+        image[0x3000] = 0x1021;
+        image[0x3001] = 0xF025;
+
+        let mut out = Vec::new();
+        format.write(&image, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn readmemh_has_every_word() {
+        let text = written(ReadMemH);
+        let words: Vec<_> = text.lines().filter(|l| !l.starts_with("//")).collect();
+
+        assert_eq!(words.len(), ADDR_SPACE_SIZE_IN_WORDS);
+        assert_eq!(words[0x3000..0x3003], ["1021", "F025", "0000"]);
+    }
+
+    #[test]
+    fn coe_vector_ends_with_a_semicolon() {
+        let text = written(Coe);
+        let words: Vec<_> = text.lines().skip(3).collect();
+
+        assert_eq!(words.len(), ADDR_SPACE_SIZE_IN_WORDS);
+        assert_eq!(words[0x3000], "1021,");
+        assert_eq!(words.last(), Some(&"0000;"));
+    }
+
+    #[test]
+    fn mif_collapses_runs() {
+        let text = written(Mif);
+        let content: Vec<_> = text
+            .lines()
+            .skip_while(|l| *l != "CONTENT BEGIN")
+            .skip(1)
+            .collect();
+
+        assert_eq!(
+            content,
+            [
+                "\t[0000..2FFF] : 0000;",
+                "\t3000 : 1021;",
+                "\t3001 : F025;",
+                "\t[3002..FFFF] : 0000;",
+                "END;",
+            ]
+        );
+    }
+}
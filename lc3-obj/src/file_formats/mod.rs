@@ -0,0 +1,296 @@
+//! Object File Formats and a trait.
+use super::Loadable;
+
+use lc3_isa::util::LoadableIterator;
+#[cfg(feature = "std")]
+use lc3_isa::util::MemoryDump;
+use lc3_isa::{Addr, Word};
+
+use alloc::string::String;
+use alloc::{vec, vec::Vec};
+use core::fmt::{self, Display};
+use core::str;
+
+#[cfg(feature = "std")]
+pub(crate) use std::io::Result as IoResult;
+#[cfg(feature = "std")]
+use std::io::{Read, Write};
+
+/// How confident a format is that a file is in that format, as a percentage.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Confidence(u8);
+
+impl Confidence {
+    /// The file is definitely not in the format.
+    pub const NONE: Confidence = Confidence(0);
+    /// The file is definitely in the format.
+    pub const CERTAIN: Confidence = Confidence(100);
+
+    /// Makes a confidence score out of a percentage. Anything over 100 is
+    /// clamped to 100.
+    pub fn new(percent: u8) -> Self {
+        Confidence(percent.min(100))
+    }
+
+    /// Returns the confidence score as a percentage.
+    pub fn percent(self) -> u8 {
+        self.0
+    }
+
+    /// Whether the format thinks the file could be in the format at all.
+    pub fn is_match(self) -> bool {
+        self > Confidence::NONE
+    }
+}
+
+impl Display for Confidence {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(fmt, "{}%", self.0)
+    }
+}
+
+/// Interface for a parseable object file format.
+///
+/// Formats work on files that are already in memory; with the `std` feature
+/// they can also be read from anything that implements `Read` (i.e. a
+/// `File`), which is read to its end.
+pub trait ObjFileFormat {
+    /// Type that Self::Return can be converted into that also can be turned
+    /// into an Iterator on Loadables (i.e. (Addr, Word) pairs).
+    ///
+    /// Put differently, this type must implement
+    /// IntoIterator<Item = (Addr, Word)>.
+    type Parsed: LoadableIterator;
+
+    /// Type that is returned upon parsing.
+    /// Must be printable, clonable, and able to be converted into a type that
+    /// can be converted into Iterator on (Addr, Word) pairs.
+    type Return: Into<Self::Parsed> + Display + Clone;
+
+    /// Human readable name of the format.
+    const NAME: &'static str;
+
+    /// Returns how confident the implementor is that the file is in the
+    /// object file format that it represents.
+    fn detect_bytes(bytes: &[u8]) -> Confidence;
+
+    /// Parses the file into the object file format's parsed type.
+    ///
+    /// Malformed files are reported with a [`ParseError`](ParseError) that
+    /// says where in the file the problem is.
+    fn parse_bytes(bytes: &[u8]) -> ParseResult<Self::Return>;
+
+    /// Like [`parse_bytes`](ObjFileFormat::parse_bytes), but keeps whatever
+    /// could be parsed from a damaged (i.e. truncated) file instead of
+    /// failing; the parts that were skipped show up in
+    /// [`warnings`](ObjFileFormat::warnings).
+    ///
+    /// Formats that can't recover from damage just parse strictly.
+    fn parse_bytes_lenient(bytes: &[u8]) -> ParseResult<Self::Return> {
+        Self::parse_bytes(bytes)
+    }
+
+    /// Things that came up while parsing a file that didn't stop it from being
+    /// parsed but are worth telling someone about. It's up to the caller to
+    /// show these (or not).
+    fn warnings(_obj: &Self::Return) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// Like [`detect_bytes`](ObjFileFormat::detect_bytes), for a file that
+    /// has to be read in first.
+    #[cfg(feature = "std")]
+    fn detect<R: Read>(file: &mut R) -> Confidence {
+        match read_all(file) {
+            Ok(bytes) => Self::detect_bytes(&bytes),
+            Err(_) => Confidence::NONE,
+        }
+    }
+
+    /// Returns true if the file can be interpreted as the object file format
+    /// that the implementor represents.
+    #[cfg(feature = "std")]
+    fn file_matches_format<R: Read>(file: &mut R) -> bool {
+        Self::detect(file).is_match()
+    }
+
+    /// Like [`parse_bytes`](ObjFileFormat::parse_bytes), for a file that has
+    /// to be read in first.
+    #[cfg(feature = "std")]
+    fn parse<R: Read>(file: &mut R) -> ParseResult<Self::Return> {
+        Self::parse_bytes(&read_all(file)?)
+    }
+
+    /// Like [`parse_bytes_lenient`](ObjFileFormat::parse_bytes_lenient), for
+    /// a file that has to be read in first.
+    #[cfg(feature = "std")]
+    fn parse_lenient<R: Read>(file: &mut R) -> ParseResult<Self::Return> {
+        Self::parse_bytes_lenient(&read_all(file)?)
+    }
+}
+
+/// Interface for an object file format that can also be written out.
+pub trait ObjFileWriter: ObjFileFormat {
+    /// Builds an object file of this format from a set of loadables.
+    fn from_loadables(loadables: Vec<Loadable>) -> Self::Return;
+
+    /// Serializes the object file.
+    fn write_bytes(obj: &Self::Return) -> WriteResult<Vec<u8>>;
+
+    /// Serializes the object file into the given file.
+    #[cfg(feature = "std")]
+    fn write<W: Write>(obj: &Self::Return, file: &mut W) -> IoResult<()> {
+        file.write_all(&Self::write_bytes(obj)?)?;
+        file.flush()
+    }
+}
+
+/// Interface for formats that describe a full memory image rather than just
+/// the words in a program, i.e. memory initialization files for HDL tools or
+/// source files to compile the image into firmware with.
+///
+/// These can only be written out.
+#[cfg(feature = "std")]
+pub trait ImageWriter {
+    /// Human readable name of the format.
+    const NAME: &'static str;
+
+    /// Writes the memory image out in this format.
+    fn write<W: Write>(&self, image: &MemoryDump, file: &mut W) -> IoResult<()>;
+}
+
+/// Interface for object files of formats that can only hold a single
+/// contiguous segment.
+pub trait SingleSegment: From<Vec<Loadable>> + Into<Vec<Loadable>> {
+    /// Returns the address of the first word in the object file, if there is
+    /// one.
+    fn orig(&self) -> Option<Addr>;
+
+    /// Splits the object file into one object file per contiguous segment.
+    ///
+    /// Object files that span multiple segments must be split up before they
+    /// can be written out.
+    fn split(self) -> Vec<Self> {
+        segments(self.into())
+            .into_iter()
+            .map(|(orig, words)| {
+                words
+                    .into_iter()
+                    .enumerate()
+                    .map(|(idx, word)| (orig.wrapping_add(idx as Addr), word))
+                    .collect::<Vec<_>>()
+                    .into()
+            })
+            .collect()
+    }
+}
+
+/// Splits loadables into segments of contiguous addresses.
+///
+/// Each segment is returned as its origin and its words. A new segment is
+/// started whenever an address doesn't directly follow the previous one; the
+/// loadables are not sorted beforehand.
+pub fn segments<L: LoadableIterator>(loadables: L) -> Vec<(Addr, Vec<Word>)> {
+    let mut segments: Vec<(Addr, Vec<Word>)> = Vec::new();
+
+    for (addr, word) in loadables {
+        match segments.last_mut() {
+            Some((orig, words)) if u32::from(*orig) + words.len() as u32 == u32::from(addr) => {
+                words.push(word)
+            }
+            _ => segments.push((addr, vec![word])),
+        }
+    }
+
+    segments
+}
+
+/// Reads the rest of a file into memory.
+#[cfg(feature = "std")]
+pub(crate) fn read_all<R: Read>(file: &mut R) -> IoResult<Vec<u8>> {
+    let mut bytes = Vec::new();
+    let _ = file.read_to_end(&mut bytes)?;
+
+    Ok(bytes)
+}
+
+/// Interprets a file as text, for text formats.
+pub(crate) fn text(bytes: &[u8]) -> ParseResult<&str> {
+    str::from_utf8(bytes).map_err(|err| ParseError::NotText {
+        at: Location::Byte(err.valid_up_to() as u64),
+    })
+}
+
+/// Groups the image into runs of repeated words: (start address, length,
+/// word).
+#[cfg(feature = "std")]
+pub(crate) fn runs(image: &MemoryDump) -> Vec<(usize, usize, Word)> {
+    let mut runs: Vec<(usize, usize, Word)> = Vec::new();
+
+    for (addr, word) in image.iter().enumerate() {
+        match runs.last_mut() {
+            Some((_, len, w)) if w == word => *len += 1,
+            _ => runs.push((addr, 1, *word)),
+        }
+    }
+
+    runs
+}
+
+mod byte_addressed;
+
+pub mod debug_info;
+pub mod error;
+pub mod ihex;
+pub mod lc3as;
+pub mod lc3tools;
+pub mod lumetta;
+pub mod mem_dump;
+#[cfg(feature = "std")]
+pub mod mem_init;
+#[cfg(feature = "std")]
+pub mod registry;
+#[cfg(feature = "std")]
+pub mod reloc;
+#[cfg(feature = "std")]
+pub mod source;
+pub mod srec;
+#[cfg(feature = "std")]
+pub mod structured;
+#[cfg(feature = "std")]
+pub mod sym;
+
+pub use debug_info::SourceLine;
+pub use error::{Location, ParseError, ParseResult, WriteError, WriteResult};
+pub use ihex::IntelHex;
+pub use lc3as::{Lc3AsBin, Lc3AsHex};
+pub use lc3tools::Lc3Tools;
+pub use lumetta::Lumetta;
+pub use mem_dump::MemDump;
+#[cfg(feature = "std")]
+pub use mem_init::{Coe, Mif, ReadMemH};
+#[cfg(feature = "std")]
+pub use registry::{Detection, Format, Object};
+#[cfg(feature = "std")]
+pub use reloc::Relocatable;
+#[cfg(feature = "std")]
+pub use source::{CArray, Layout, RustStatic};
+pub use srec::SRecord;
+#[cfg(feature = "std")]
+pub use structured::{Json, Yaml};
+#[cfg(feature = "std")]
+pub use sym::SymbolTable;
+
+/// Returns the one segment the loadables make up, for formats that can only
+/// hold a single segment.
+///
+/// Errors if there are no loadables or if they span multiple segments.
+pub(crate) fn only_segment<L: LoadableIterator>(loadables: L) -> WriteResult<(Addr, Vec<Word>)> {
+    let mut segments = segments(loadables);
+
+    match (segments.pop(), segments.is_empty()) {
+        (Some(segment), true) => Ok(segment),
+        (None, _) => Err(WriteError::Empty),
+        (Some(_), false) => Err(WriteError::MultipleSegments),
+    }
+}
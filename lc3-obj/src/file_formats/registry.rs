@@ -0,0 +1,595 @@
+//! The set of formats obj-conv can convert between and a format independent
+//! representation of an object file.
+use super::ihex::IntelHexObjFile;
+use super::lc3as::Lc3AsObjFile;
+use super::lc3tools::Lc3ToolsObjFile;
+use super::lumetta::LumettaObjFile;
+use super::mem_dump::MemDumpObjFile;
+use super::srec::SRecordObjFile;
+use super::structured::StructuredObjFile;
+use super::sym::SymbolTable;
+use super::{
+    CArray, Coe, Confidence, ImageWriter, IntelHex, IoResult, Json, Lc3AsBin, Lc3AsHex, Lc3Tools,
+    Loadable, Lumetta, MemDump, Mif, ObjFileFormat, ReadMemH, Relocatable, RustStatic, SRecord,
+    SourceLine, Yaml,
+};
+
+use lc3_isa::Addr;
+
+use std::collections::BTreeMap;
+use std::fmt::{self, Display};
+use std::fs;
+use std::ops::RangeInclusive;
+use std::path::Path;
+use std::str::FromStr;
+
+/// A parsed object file, stripped of everything that's specific to the format
+/// it came from.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Object {
+    /// The words in the object file and the addresses they go to.
+    pub loadables: Vec<Loadable>,
+    /// Source lines for the words in the object file, for formats that have
+    /// them.
+    pub source_lines: BTreeMap<Addr, SourceLine>,
+    /// Labels for addresses in the object file, if a symbol table was given.
+    pub symbols: SymbolTable,
+}
+
+impl Object {
+    /// Returns what would be lost by writing this object out in the given
+    /// format (i.e. `"source lines"`), if anything.
+    pub fn lost_in(&self, format: Format) -> Vec<&'static str> {
+        let has_source_lines = self.source_lines.values().any(|l| !l.text.is_empty());
+        let has_symbols = !self.symbols.is_empty();
+
+        let mut lost = Vec::new();
+        if has_source_lines && format.drops_source_lines() {
+            lost.push("source lines");
+        }
+        if has_symbols && format.drops_symbols() {
+            lost.push("symbols");
+        }
+
+        lost
+    }
+
+    /// Drops zeros before the first and after the last non-zero word, for
+    /// images (like memory dumps) where unused memory can't be told apart
+    /// from memory that was zeroed on purpose.
+    pub fn trim_zeros(&mut self) {
+        let used = self
+            .loadables
+            .iter()
+            .filter(|(_, word)| *word != 0)
+            .map(|(addr, _)| *addr);
+
+        match (used.clone().min(), used.max()) {
+            (Some(start), Some(end)) => self.retain_region(start..=end),
+            _ => {
+                self.loadables.clear();
+                self.source_lines.clear();
+            }
+        }
+    }
+
+    /// Drops everything that's outside of the given region.
+    pub fn retain_region(&mut self, region: RangeInclusive<Addr>) {
+        self.loadables.retain(|(addr, _)| region.contains(addr));
+
+        let source_lines = std::mem::take(&mut self.source_lines);
+        self.source_lines = source_lines
+            .into_iter()
+            .filter(|(addr, _)| region.contains(addr))
+            .collect();
+    }
+}
+
+impl From<Vec<Loadable>> for Object {
+    fn from(loadables: Vec<Loadable>) -> Self {
+        Object {
+            loadables,
+            ..Object::default()
+        }
+    }
+}
+
+impl From<Lc3ToolsObjFile<'_>> for Object {
+    fn from(obj: Lc3ToolsObjFile<'_>) -> Self {
+        Object {
+            source_lines: obj.source_lines(),
+            loadables: obj.into(),
+            ..Object::default()
+        }
+    }
+}
+
+impl From<LumettaObjFile<'_>> for Object {
+    fn from(obj: LumettaObjFile<'_>) -> Self {
+        Vec::<Loadable>::from(obj).into()
+    }
+}
+
+impl From<MemDumpObjFile> for Object {
+    fn from(obj: MemDumpObjFile) -> Self {
+        Vec::<Loadable>::from(obj).into()
+    }
+}
+
+impl From<IntelHexObjFile> for Object {
+    fn from(obj: IntelHexObjFile) -> Self {
+        Vec::<Loadable>::from(obj).into()
+    }
+}
+
+impl From<SRecordObjFile> for Object {
+    fn from(obj: SRecordObjFile) -> Self {
+        Vec::<Loadable>::from(obj).into()
+    }
+}
+
+impl From<Lc3AsObjFile> for Object {
+    fn from(obj: Lc3AsObjFile) -> Self {
+        Vec::<Loadable>::from(obj).into()
+    }
+}
+
+impl From<StructuredObjFile> for Object {
+    fn from(obj: StructuredObjFile) -> Self {
+        let mut symbols = SymbolTable::default();
+        obj.symbols
+            .iter()
+            .for_each(|(label, addr)| symbols.insert(label, *addr));
+
+        // Words are in the order they were in the file:
+        let source_lines = obj
+            .placed_words()
+            .enumerate()
+            .filter_map(|(idx, (addr, w))| {
+                let text = w.source.clone()?;
+                Some((
+                    addr,
+                    SourceLine {
+                        text,
+                        ordinal: idx + 1,
+                    },
+                ))
+            })
+            .collect();
+
+        Object {
+            source_lines,
+            symbols,
+            loadables: obj.into(),
+        }
+    }
+}
+
+impl From<Object> for Vec<Loadable> {
+    fn from(obj: Object) -> Self {
+        obj.loadables
+    }
+}
+
+/// The formats obj-conv knows about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Format {
+    /// [`Lc3Tools`](super::Lc3Tools) object files.
+    Lc3Tools,
+    /// [`Lumetta`](super::Lumetta) object files.
+    Lumetta,
+    /// [`MemDump`](super::MemDump) full memory images.
+    MemoryDump,
+    /// [`IntelHex`](super::IntelHex) files.
+    IntelHex,
+    /// [`SRecord`](super::SRecord) files.
+    SRecord,
+    /// [`Lc3AsHex`](super::Lc3AsHex) files.
+    Lc3AsHex,
+    /// [`Lc3AsBin`](super::Lc3AsBin) files.
+    Lc3AsBin,
+    /// [`ReadMemH`](super::ReadMemH) memory initialization files.
+    ReadMemH,
+    /// [`Coe`](super::Coe) memory initialization files.
+    Coe,
+    /// [`Mif`](super::Mif) memory initialization files.
+    Mif,
+    /// [`CArray`](super::CArray) C headers.
+    CArray,
+    /// [`RustStatic`](super::RustStatic) Rust source files.
+    RustStatic,
+    /// [`Relocatable`](super::Relocatable) object files, which have to be
+    /// linked.
+    Relocatable,
+    /// [`Json`](super::Json) dumps.
+    Json,
+    /// [`Yaml`](super::Yaml) dumps.
+    Yaml,
+    /// LC-3 assembly, from the [disassembler](crate::disasm).
+    Asm,
+    /// Control-flow graphs, as [Graphviz DOT](crate::dot) files.
+    Dot,
+}
+
+impl Format {
+    /// Every format. When detection scores tie, formats that come first win.
+    pub const ALL: [Format; 17] = [
+        Format::Lc3Tools,
+        Format::Relocatable,
+        Format::Json,
+        Format::Yaml,
+        Format::IntelHex,
+        Format::SRecord,
+        Format::Lc3AsBin,
+        Format::Lc3AsHex,
+        Format::MemoryDump,
+        Format::Lumetta,
+        Format::ReadMemH,
+        Format::Coe,
+        Format::Mif,
+        Format::CArray,
+        Format::RustStatic,
+        Format::Asm,
+        Format::Dot,
+    ];
+
+    /// Short name of the format, as used on the command line.
+    pub fn name(self) -> &'static str {
+        use Format::*;
+
+        match self {
+            Lc3Tools => "lc3tools",
+            Lumetta => "lumetta",
+            MemoryDump => "memdump",
+            IntelHex => "ihex",
+            SRecord => "srec",
+            Lc3AsHex => "lc3as-hex",
+            Lc3AsBin => "lc3as-bin",
+            ReadMemH => "readmemh",
+            Coe => "coe",
+            Mif => "mif",
+            CArray => "c",
+            RustStatic => "rust",
+            Relocatable => "reloc",
+            Json => "json",
+            Yaml => "yaml",
+            Asm => "asm",
+            Dot => "dot",
+        }
+    }
+
+    /// Human readable description of the format.
+    pub fn description(self) -> &'static str {
+        match self {
+            Format::Lc3Tools => Lc3Tools::NAME,
+            Format::Lumetta => <&Lumetta>::NAME,
+            Format::MemoryDump => <MemDump as ObjFileFormat>::NAME,
+            Format::IntelHex => IntelHex::NAME,
+            Format::SRecord => SRecord::NAME,
+            Format::Lc3AsHex => Lc3AsHex::NAME,
+            Format::Lc3AsBin => Lc3AsBin::NAME,
+            Format::ReadMemH => ReadMemH::NAME,
+            Format::Coe => Coe::NAME,
+            Format::Mif => Mif::NAME,
+            Format::CArray => CArray::NAME,
+            Format::RustStatic => RustStatic::NAME,
+            Format::Relocatable => Relocatable::NAME,
+            Format::Json => Json::NAME,
+            Format::Yaml => Yaml::NAME,
+            Format::Asm => crate::disasm::NAME,
+            Format::Dot => crate::dot::NAME,
+        }
+    }
+
+    /// Whether writing a program out in this format loses its source lines.
+    ///
+    /// Memory dumps, memory initialization files, and source files are images
+    /// to load rather than object files; their source lines go in a debug
+    /// info sidecar instead. Disassembly keeps them as comments, and
+    /// control-flow graphs are a picture of the program rather than a copy of
+    /// it.
+    pub fn drops_source_lines(self) -> bool {
+        use Format::*;
+
+        matches!(self, Lumetta | IntelHex | SRecord | Lc3AsHex | Lc3AsBin)
+    }
+
+    /// Whether writing a program out in this format loses its symbols.
+    ///
+    /// Dumps keep them and disassembly uses them for labels; control-flow
+    /// graphs are a picture of the program rather than a copy of it. Every
+    /// other format only has room for words.
+    pub fn drops_symbols(self) -> bool {
+        use Format::*;
+
+        !matches!(self, Json | Yaml | Asm | Dot)
+    }
+
+    /// File extensions that files of this format usually have.
+    ///
+    /// Note that multiple formats can share an extension.
+    pub fn extensions(self) -> &'static [&'static str] {
+        use Format::*;
+
+        match self {
+            Lc3Tools | Lumetta => &["obj"],
+            MemoryDump => &["mem"],
+            IntelHex => &["hex", "ihex", "ihx"],
+            SRecord => &["srec", "s19", "s28", "mot"],
+            Lc3AsHex => &["hex"],
+            Lc3AsBin => &["bin"],
+            ReadMemH => &["memh", "vmem"],
+            Coe => &["coe"],
+            Mif => &["mif"],
+            CArray => &["h", "c"],
+            RustStatic => &["rs"],
+            Relocatable => &["rel"],
+            Json => &["json"],
+            Yaml => &["yaml", "yml"],
+            Asm => &["asm"],
+            Dot => &["dot", "gv"],
+        }
+    }
+
+    /// Whether obj-conv can parse files of this format.
+    pub fn can_read(self) -> bool {
+        use Format::*;
+
+        match self {
+            Lc3Tools | Lumetta | MemoryDump | IntelHex | SRecord | Lc3AsHex | Lc3AsBin
+            | Relocatable | Json | Yaml => true,
+            ReadMemH | Coe | Mif | CArray | RustStatic | Asm | Dot => false,
+        }
+    }
+
+    /// Whether this format describes a full memory image (and so gets the
+    /// program layered onto an OS) rather than just the words in a program.
+    pub fn is_image(self) -> bool {
+        use Format::*;
+
+        match self {
+            MemoryDump | ReadMemH | Coe | Mif | CArray | RustStatic => true,
+            Lc3Tools | Lumetta | IntelHex | SRecord | Lc3AsHex | Lc3AsBin | Relocatable | Json
+            | Yaml | Asm | Dot => false,
+        }
+    }
+
+    /// Runs this format's detector on the contents of a file.
+    pub fn detect_bytes(self, bytes: &[u8]) -> Confidence {
+        match self {
+            Format::Lc3Tools => Lc3Tools::detect_bytes(bytes),
+            Format::Lumetta => <&Lumetta>::detect_bytes(bytes),
+            Format::MemoryDump => MemDump::detect_bytes(bytes),
+            Format::IntelHex => IntelHex::detect_bytes(bytes),
+            Format::SRecord => SRecord::detect_bytes(bytes),
+            Format::Lc3AsHex => Lc3AsHex::detect_bytes(bytes),
+            Format::Lc3AsBin => Lc3AsBin::detect_bytes(bytes),
+            Format::Relocatable => Relocatable::detect_bytes(bytes),
+            Format::Json => Json::detect_bytes(bytes),
+            Format::Yaml => Yaml::detect_bytes(bytes),
+            Format::ReadMemH
+            | Format::Coe
+            | Format::Mif
+            | Format::CArray
+            | Format::RustStatic
+            | Format::Asm
+            | Format::Dot => Confidence::NONE,
+        }
+    }
+
+    /// Returns all the formats that use the extension of the given file.
+    pub fn candidates_for_extension<P: AsRef<Path>>(path: P) -> Vec<Format> {
+        let ext = match path.as_ref().extension().and_then(|e| e.to_str()) {
+            Some(ext) => ext.to_lowercase(),
+            None => return Vec::new(),
+        };
+
+        Format::ALL
+            .iter()
+            .filter(|f| f.extensions().contains(&ext.as_str()))
+            .copied()
+            .collect()
+    }
+
+    /// Guesses the format of a file from its extension.
+    ///
+    /// Returns `None` if no format or more than one format uses the extension.
+    pub fn from_extension<P: AsRef<Path>>(path: P) -> Option<Format> {
+        match Format::candidates_for_extension(path).as_slice() {
+            [format] => Some(*format),
+            _ => None,
+        }
+    }
+}
+
+impl Display for Format {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(fmt, "{}", self.name())
+    }
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Format::ALL
+            .iter()
+            .find(|f| f.name().eq_ignore_ascii_case(s))
+            .copied()
+            .ok_or_else(|| format!("Unknown format: `{}`.", s))
+    }
+}
+
+/// The results of running every format's detector on a file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Detection {
+    /// Every format along with how confident it is, best match first.
+    pub candidates: Vec<(Format, Confidence)>,
+}
+
+impl Detection {
+    /// Matches that score within this many points of the best match are
+    /// considered to be contenders.
+    pub const AMBIGUITY_MARGIN: u8 = 15;
+
+    /// The best match has to score at least this much to be picked without
+    /// being asked for; anything less is too much of a guess.
+    pub const MIN_CONFIDENCE: u8 = 50;
+
+    /// Runs every (readable) format's detector on the file at the given path.
+    pub fn run<P: AsRef<Path>>(path: P) -> IoResult<Self> {
+        Ok(Detection::run_on(&fs::read(path)?))
+    }
+
+    /// Runs every (readable) format's detector on the contents of a file.
+    pub fn run_on(bytes: &[u8]) -> Self {
+        let mut candidates: Vec<_> = Format::ALL
+            .iter()
+            .filter(|f| f.can_read())
+            .map(|f| (*f, f.detect_bytes(bytes)))
+            .collect();
+
+        // Stable, so ties go to the format that comes first in `Format::ALL`:
+        candidates.sort_by(|(_, a), (_, b)| b.cmp(a));
+
+        Detection { candidates }
+    }
+
+    /// The format that's the best match for the file, if any format matches
+    /// well enough (see [`MIN_CONFIDENCE`](Detection::MIN_CONFIDENCE)).
+    pub fn best(&self) -> Option<Format> {
+        self.candidates
+            .first()
+            .filter(|(_, c)| c.percent() >= Self::MIN_CONFIDENCE)
+            .map(|(f, _)| *f)
+    }
+
+    /// The best match for the file if it matches, but not well enough to be
+    /// picked as the [`best`](Detection::best) match.
+    pub fn weak_match(&self) -> Option<(Format, Confidence)> {
+        self.candidates
+            .first()
+            .filter(|(_, c)| c.is_match() && c.percent() < Self::MIN_CONFIDENCE)
+            .copied()
+    }
+
+    /// Other formats that match almost as well as the best match.
+    ///
+    /// If this isn't empty, the file's format is ambiguous.
+    pub fn contenders(&self) -> Vec<(Format, Confidence)> {
+        let best = match self.candidates.first() {
+            Some((_, best)) if best.percent() >= Self::MIN_CONFIDENCE => best.percent(),
+            _ => return Vec::new(),
+        };
+
+        self.candidates
+            .iter()
+            .skip(1)
+            .filter(|(_, c)| c.is_match() && best - c.percent() <= Self::AMBIGUITY_MARGIN)
+            .copied()
+            .collect()
+    }
+}
+
+impl Display for Detection {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.candidates.iter().try_for_each(|(format, confidence)| {
+            writeln!(
+                fmt,
+                "{:>4}  {:<10} {}",
+                confidence.to_string(),
+                format.name(),
+                format.description()
+            )
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_formats::ObjFileWriter;
+
+    #[test]
+    fn formats_with_headers_win() {
+        let program = vec![(0x3000, 0x1021), (0x3001, 0xF025)];
+        let obj = Lc3Tools::from_loadables(program.clone());
+        let detection = Detection::run_on(&Lc3Tools::write_bytes(&obj).unwrap());
+
+        assert_eq!(detection.best(), Some(Format::Lc3Tools));
+        assert_eq!(detection.candidates[0].1, Confidence::CERTAIN);
+
+        let obj = SRecord::from_loadables(program);
+        let detection = Detection::run_on(&SRecord::write_bytes(&obj).unwrap());
+
+        assert_eq!(detection.best(), Some(Format::SRecord));
+        assert!(detection.contenders().is_empty());
+    }
+
+    #[test]
+    fn json_dumps_are_not_yaml() {
+        let obj = Json::from_loadables(vec![(0x3000, 0x1021), (0x3001, 0xF025)]);
+        let detection = Detection::run_on(&Json::write_bytes(&obj).unwrap());
+
+        assert_eq!(detection.best(), Some(Format::Json));
+        assert!(detection.contenders().is_empty());
+
+        let detection = Detection::run_on(&Yaml::write_bytes(&obj).unwrap());
+        assert_eq!(detection.best(), Some(Format::Yaml));
+        assert!(detection.contenders().is_empty());
+    }
+
+    #[test]
+    fn candidates_are_ranked() {
+        let detection = Detection::run_on(&[0x30, 0x00, 0x12, 0x61, 0x10, 0x21, 0xF0, 0x25]);
+
+        assert_eq!(detection.best(), Some(Format::Lumetta));
+        assert!(detection
+            .candidates
+            .windows(2)
+            .all(|pair| pair[0].1 >= pair[1].1));
+    }
+
+    #[test]
+    fn lost_in() {
+        let mut object = Object::from(vec![(0x3000, 0x1021)]);
+        assert!(object.lost_in(Format::IntelHex).is_empty());
+
+        let line = SourceLine {
+            text: "ADD R0, R0, #1".to_string(),
+            ordinal: 2,
+        };
+        let _ = object.source_lines.insert(0x3000, line);
+        assert_eq!(object.lost_in(Format::IntelHex), ["source lines"]);
+        assert!(object.lost_in(Format::Lc3Tools).is_empty());
+        assert!(object.lost_in(Format::Mif).is_empty());
+
+        object.symbols.insert("MAIN", 0x3000);
+        assert_eq!(
+            object.lost_in(Format::IntelHex),
+            ["source lines", "symbols"]
+        );
+        assert_eq!(object.lost_in(Format::Mif), ["symbols"]);
+        assert_eq!(object.lost_in(Format::Lc3Tools), ["symbols"]);
+        assert!(object.lost_in(Format::Json).is_empty());
+        assert!(object.lost_in(Format::Asm).is_empty());
+    }
+
+    #[test]
+    fn weak_matches_are_not_picked() {
+        let detection = Detection::run_on(b"hello world!");
+
+        assert_eq!(detection.best(), None);
+        assert!(detection.contenders().is_empty());
+        // Every word decodes (as an `LDR` or `STR`) and the origin is in user
+        // space, but it's all text:
+        assert_eq!(
+            detection.weak_match(),
+            Some((Format::Lumetta, Confidence::new(20)))
+        );
+
+        let detection = Detection::run_on(&[0xFF; 3]);
+        assert_eq!(detection.best(), None);
+        assert_eq!(detection.weak_match(), None);
+    }
+}
@@ -0,0 +1,431 @@
+//! obj-conv's own relocatable object format: sections of words that don't
+//! have to have a fixed origin, the symbols they define, and the places in them
+//! that refer to symbols (relocations), so that they can be put together by the
+//! [`linker`](crate::linker).
+//!
+//! These are text files that look like this:
+//! ```text
+//! .LC3REL
+//! ; Prints the string in R0 and a newline.
+//! .IMPORT NEWLINE
+//! .SECTION print_line
+//! .WORDS F022 4800 F025
+//! .EXPORT PRINT_LINE 0
+//! .RELOC 1 PC11 NEWLINE
+//! ```
+//!
+//! - `.SECTION <name>` starts a new section; everything up to the next
+//!   `.SECTION` belongs to it.
+//! - `.ORIG <addr>` pins the current section to an address. Sections without
+//!   one are placed by the linker.
+//! - `.WORDS <word>...` appends words (hex) to the current section.
+//! - `.EXPORT <label> <offset>` and `.LOCAL <label> <offset>` define a label
+//!   at a word in the current section; exported labels are visible to other
+//!   files.
+//! - `.IMPORT <label>` declares a label that another file exports.
+//! - `.RELOC <offset> <PC9 | PC11 | FILL> <label> [addend]` says that the
+//!   PCoffset9 or PCoffset11 field of a word (or the whole word, for `FILL`)
+//!   should refer to a label (plus `addend`).
+//!
+//! Addresses, words, and offsets (which word of the section, starting from 0)
+//! are all hex, with or without an `x` or `0x` prefix; addends are decimal and
+//! can be negative. Comments start with `;`.
+use super::{text, Confidence, Location, ParseError, ParseResult};
+
+use lc3_isa::{Addr, Word};
+
+use std::fmt::{self, Display};
+use std::str::FromStr;
+
+/// What part of a word a relocation fills in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum RelocKind {
+    /// The PCoffset9 field of a `BR`, `LD`, `LDI`, `LEA`, `ST`, or `STI`.
+    PcOffset9,
+    /// The PCoffset11 field of a `JSR`.
+    PcOffset11,
+    /// The whole word is the label's address (i.e. a `.FILL`).
+    Fill,
+}
+
+impl RelocKind {
+    /// Whether the word is an instruction that has the field this kind of
+    /// relocation fills in. Anything can be a `FILL`.
+    pub fn applies_to(self, word: Word) -> bool {
+        match self {
+            // BR, LD, ST, LDI, STI, and LEA:
+            RelocKind::PcOffset9 => matches!(word >> 12, 0x0 | 0x2 | 0x3 | 0xA | 0xB | 0xE),
+            // JSR (but not JSRR):
+            RelocKind::PcOffset11 => word >> 11 == 0b01001,
+            RelocKind::Fill => true,
+        }
+    }
+
+    /// Short name of the relocation kind, as used in files.
+    pub fn name(self) -> &'static str {
+        match self {
+            RelocKind::PcOffset9 => "PC9",
+            RelocKind::PcOffset11 => "PC11",
+            RelocKind::Fill => "FILL",
+        }
+    }
+}
+
+impl Display for RelocKind {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(fmt, "{}", self.name())
+    }
+}
+
+impl FromStr for RelocKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        [RelocKind::PcOffset9, RelocKind::PcOffset11, RelocKind::Fill]
+            .iter()
+            .find(|k| k.name().eq_ignore_ascii_case(s))
+            .copied()
+            .ok_or_else(|| format!("unknown relocation kind `{}`", s))
+    }
+}
+
+/// A label defined in a section.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Symbol {
+    /// The label.
+    pub name: String,
+    /// Which word in the section the label is on (hex, in files).
+    pub offset: Addr,
+    /// Whether other files can refer to the label.
+    pub exported: bool,
+}
+
+/// A word in a section that refers to a label.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Relocation {
+    /// Which word in the section refers to the label (hex, in files).
+    pub offset: Addr,
+    /// What part of the word to fill in.
+    pub kind: RelocKind,
+    /// The label being referred to.
+    pub symbol: String,
+    /// Added to the label's address.
+    pub addend: i32,
+}
+
+/// A run of words that's placed in memory as a unit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Section {
+    /// Name of the section; only used in messages.
+    pub name: String,
+    /// Where the section has to go, if it's pinned to an address.
+    pub orig: Option<Addr>,
+    /// The words in the section.
+    pub words: Vec<Word>,
+    /// Labels on words in the section.
+    pub symbols: Vec<Symbol>,
+    /// Words in the section that refer to labels.
+    pub relocations: Vec<Relocation>,
+}
+
+impl Section {
+    fn new(name: &str) -> Self {
+        Section {
+            name: name.to_string(),
+            orig: None,
+            words: Vec::new(),
+            symbols: Vec::new(),
+            relocations: Vec::new(),
+        }
+    }
+}
+
+/// Object File type for [`Relocatable`](Relocatable).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RelocatableObjFile {
+    /// The sections in the file, in order.
+    pub sections: Vec<Section>,
+    /// Labels the file uses that other files define.
+    pub imports: Vec<String>,
+}
+
+impl Display for RelocatableObjFile {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.imports
+            .iter()
+            .try_for_each(|i| writeln!(fmt, "<import>: {}", i))?;
+
+        self.sections.iter().try_for_each(|s| {
+            match s.orig {
+                Some(orig) => writeln!(fmt, "<section {} @ {:04X}>", s.name, orig)?,
+                None => writeln!(fmt, "<section {}>", s.name)?,
+            }
+
+            s.words.iter().enumerate().try_for_each(|(idx, word)| {
+                write!(fmt, "  +{:04X}: {:04X}", idx, word)?;
+
+                s.symbols
+                    .iter()
+                    .filter(|sym| sym.offset as usize == idx)
+                    .try_for_each(|sym| write!(fmt, "  {}:", sym.name))?;
+                s.relocations
+                    .iter()
+                    .filter(|r| r.offset as usize == idx)
+                    .try_for_each(|r| write!(fmt, "  ; {} {}{:+}", r.kind, r.symbol, r.addend))?;
+
+                writeln!(fmt)
+            })
+        })
+    }
+}
+
+/// Parser for obj-conv's relocatable object files. These can only be linked
+/// (see the [`linker`](crate::linker)), so this isn't an
+/// [`ObjFileFormat`](super::ObjFileFormat).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Relocatable;
+
+impl Relocatable {
+    /// Human readable name of the format.
+    pub const NAME: &'static str = "an obj-conv Relocatable Object File";
+
+    const MAGIC: &'static str = ".LC3REL";
+
+    /// Strips comments and whitespace from the lines of the file, keeping
+    /// track of line numbers.
+    fn lines(text: &str) -> impl Iterator<Item = (Location, &str)> {
+        text.lines()
+            .enumerate()
+            .map(|(idx, l)| {
+                let code = l.split_once(';').map_or(l, |(code, _)| code);
+                (Location::Line(idx + 1), code.trim())
+            })
+            .filter(|(_, l)| !l.is_empty())
+    }
+
+    /// Returns how confident we are that the file is a relocatable object file.
+    ///
+    /// These start with a magic line so this is all or nothing.
+    pub fn detect_bytes(bytes: &[u8]) -> Confidence {
+        let text = match text(bytes) {
+            Ok(text) => text,
+            Err(_) => return Confidence::NONE,
+        };
+
+        let first = Relocatable::lines(text).next();
+        match first {
+            Some((_, l)) if l.eq_ignore_ascii_case(Relocatable::MAGIC) => Confidence::CERTAIN,
+            _ => Confidence::NONE,
+        }
+    }
+
+    /// Parses a relocatable object file.
+    pub fn parse_bytes(bytes: &[u8]) -> ParseResult<RelocatableObjFile> {
+        let mut lines = Relocatable::lines(text(bytes)?);
+        match lines.next() {
+            Some((_, l)) if l.eq_ignore_ascii_case(Relocatable::MAGIC) => {}
+            _ => {
+                return Err(ParseError::BadHeader {
+                    at: Location::Line(1),
+                })
+            }
+        }
+
+        let mut obj = RelocatableObjFile::default();
+        // Offsets that have to be checked once the whole section is read, along
+        // with where they came from:
+        let mut offsets: Vec<(usize, Addr, Location)> = Vec::new();
+
+        for (at, line) in lines {
+            let bad = |reason: String| ParseError::BadRecord { at, reason };
+
+            let num = |s: &str| {
+                let s = s.trim_start_matches("0x").trim_start_matches('x');
+                Addr::from_str_radix(s, 16).map_err(|e| bad(format!("`{}`: {}", s, e)))
+            };
+
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let (directive, args) = (fields[0].to_uppercase(), &fields[1..]);
+
+            if directive == ".SECTION" || directive == ".IMPORT" {
+                match args {
+                    [name] if directive == ".SECTION" => obj.sections.push(Section::new(name)),
+                    [name] => obj.imports.push(name.to_string()),
+                    _ => return Err(bad(format!("expected `{} <name>`", directive))),
+                }
+
+                continue;
+            }
+
+            let idx = obj.sections.len();
+            let section = obj
+                .sections
+                .last_mut()
+                .ok_or_else(|| bad(format!("`{}` outside of a section", directive)))?;
+
+            match (directive.as_str(), args) {
+                (".ORIG", [addr]) => section.orig = Some(num(addr)?),
+                (".WORDS", words) => {
+                    for word in words.iter() {
+                        section.words.push(num(word)?);
+                    }
+                }
+                (".EXPORT", [name, off]) | (".LOCAL", [name, off]) => {
+                    let off = num(off)?;
+                    offsets.push((idx - 1, off, at));
+
+                    section.symbols.push(Symbol {
+                        name: name.to_string(),
+                        offset: off,
+                        exported: directive == ".EXPORT",
+                    });
+                }
+                (".RELOC", args) if args.len() == 3 || args.len() == 4 => {
+                    let off = num(args[0])?;
+                    offsets.push((idx - 1, off, at));
+
+                    section.relocations.push(Relocation {
+                        offset: off,
+                        kind: args[1].parse().map_err(bad)?,
+                        symbol: args[2].to_string(),
+                        addend: match args.get(3) {
+                            Some(a) => a
+                                .parse()
+                                .map_err(|e| bad(format!("bad addend `{}`: {}", a, e)))?,
+                            None => 0,
+                        },
+                    });
+                }
+                _ => return Err(bad(format!("unknown or malformed directive `{}`", line))),
+            }
+        }
+
+        for (section, off, at) in offsets {
+            let section = &obj.sections[section];
+
+            if off as usize >= section.words.len() {
+                return Err(ParseError::BadRecord {
+                    at,
+                    reason: format!(
+                        "offset {:X} is past the end of section `{}` ({:#X} words)",
+                        off,
+                        section.name,
+                        section.words.len()
+                    ),
+                });
+            }
+        }
+
+        Ok(obj)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The line a malformed file fails on.
+    fn bad_line(text: &str) -> usize {
+        match Relocatable::parse_bytes(text.as_bytes()) {
+            Err(ParseError::BadRecord {
+                at: Location::Line(line),
+                ..
+            }) => line,
+            other => panic!("expected a bad record, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn example() {
+        let text = "
+            .LC3REL
+            ; Prints the string in R0 and a newline.
+            .IMPORT NEWLINE
+            .SECTION print_line
+            .WORDS F022 4800 F025
+            .EXPORT PRINT_LINE 0
+            .RELOC 1 PC11 NEWLINE
+        ";
+        let obj = Relocatable::parse_bytes(text.as_bytes()).unwrap();
+
+        assert_eq!(obj.imports, ["NEWLINE"]);
+        assert_eq!(
+            obj.sections,
+            [Section {
+                name: "print_line".to_string(),
+                orig: None,
+                words: vec![0xF022, 0x4800, 0xF025],
+                symbols: vec![Symbol {
+                    name: "PRINT_LINE".to_string(),
+                    offset: 0,
+                    exported: true,
+                }],
+                relocations: vec![Relocation {
+                    offset: 1,
+                    kind: RelocKind::PcOffset11,
+                    symbol: "NEWLINE".to_string(),
+                    addend: 0,
+                }],
+            }]
+        );
+    }
+
+    #[test]
+    fn offsets_and_words_are_hex() {
+        let text = ".LC3REL\n.SECTION s\n.ORIG x3000\n.WORDS 0x1 A 1F\n.LOCAL END 0x2\n";
+        let obj = Relocatable::parse_bytes(text.as_bytes()).unwrap();
+
+        assert_eq!(obj.sections[0].orig, Some(0x3000));
+        assert_eq!(obj.sections[0].words, [0x1, 0xA, 0x1F]);
+        assert_eq!(obj.sections[0].symbols[0].offset, 2);
+    }
+
+    #[test]
+    fn bad_headers() {
+        assert!(matches!(
+            Relocatable::parse_bytes(b".SECTION s\n.WORDS 0\n"),
+            Err(ParseError::BadHeader {
+                at: Location::Line(1)
+            })
+        ));
+        assert!(matches!(
+            Relocatable::parse_bytes(b"\xFF"),
+            Err(ParseError::NotText { .. })
+        ));
+    }
+
+    #[test]
+    fn malformed_lines() {
+        let header = ".LC3REL\n\n; comment\n.SECTION s\n.WORDS 0E00 0000\n";
+
+        // Blank lines and comments still count:
+        assert_eq!(bad_line(&format!("{}.IMPORT\n", header)), 6);
+        assert_eq!(bad_line(&format!("{}.IMPORT A B\n", header)), 6);
+        assert_eq!(bad_line(&format!("{}.EXPORT MAIN\n", header)), 6);
+        assert_eq!(bad_line(&format!("{}.LOCAL MAIN 0 1\n", header)), 6);
+        assert_eq!(bad_line(&format!("{}.EXPORT MAIN zz\n", header)), 6);
+        assert_eq!(bad_line(&format!("{}.RELOC 0 PC9\n", header)), 6);
+        assert_eq!(bad_line(&format!("{}.RELOC 0 PC10 MAIN\n", header)), 6);
+        assert_eq!(bad_line(&format!("{}.RELOC 0 PC9 MAIN x\n", header)), 6);
+        assert_eq!(bad_line(&format!("{}.WORDS 1\n.WORDS 10000\n", header)), 7);
+        assert_eq!(bad_line(&format!("{}.ENTRY 0\n", header)), 6);
+
+        assert_eq!(bad_line(".LC3REL\n.WORDS 0\n"), 2);
+        assert_eq!(bad_line(".LC3REL\n.SECTION\n"), 2);
+    }
+
+    #[test]
+    fn offsets_past_the_end() {
+        let header = ".LC3REL\n.SECTION s\n.WORDS 0E00 0000\n";
+
+        assert_eq!(bad_line(&format!("{}.EXPORT MAIN 2\n", header)), 4);
+        assert_eq!(
+            bad_line(&format!("{}.LOCAL A 0\n.RELOC 2 PC9 A\n", header)),
+            5
+        );
+
+        // Offsets are checked once the whole section has been read:
+        let text = format!("{}.LOCAL END 2\n.WORDS 0\n", header);
+        assert!(Relocatable::parse_bytes(text.as_bytes()).is_ok());
+    }
+}
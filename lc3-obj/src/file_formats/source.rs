@@ -0,0 +1,325 @@
+//! [`ImageWriter`](super::ImageWriter) implementations that render memory
+//! images as source code (a C header or a Rust module) so they can be baked
+//! into firmware.
+//!
+//! Since most of the 64K address space is usually empty, the image can be laid
+//! out in full, as just its non-zero segments, or run-length encoded (see
+//! [`Layout`](Layout)).
+use super::{runs, segments, ImageWriter, IoResult};
+
+use lc3_isa::util::MemoryDump;
+use lc3_isa::{Addr, Word, ADDR_SPACE_SIZE_IN_WORDS};
+
+use std::fmt::{self, Display};
+use std::io::Write;
+use std::str::FromStr;
+
+/// How many words to put on each line of an array.
+const WORDS_PER_LINE: usize = 8;
+
+/// How a memory image is laid out in source code.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Layout {
+    /// Every word in the address space, as one array.
+    #[default]
+    Full,
+    /// Only the segments of non-zero words, each as its own array, along with
+    /// their addresses. Everything else is zero.
+    Sparse,
+    /// Runs of repeated words, as (start address, length, word) triples.
+    Rle,
+}
+
+impl Layout {
+    /// Every layout.
+    pub const ALL: [Layout; 3] = [Layout::Full, Layout::Sparse, Layout::Rle];
+
+    /// Short name of the layout, as used on the command line.
+    pub fn name(self) -> &'static str {
+        match self {
+            Layout::Full => "full",
+            Layout::Sparse => "sparse",
+            Layout::Rle => "rle",
+        }
+    }
+}
+
+impl Display for Layout {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(fmt, "{}", self.name())
+    }
+}
+
+impl FromStr for Layout {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Layout::ALL
+            .iter()
+            .find(|l| l.name().eq_ignore_ascii_case(s))
+            .copied()
+            .ok_or_else(|| format!("Unknown layout: `{}`.", s))
+    }
+}
+
+/// The segments of non-zero words in the image.
+fn non_zero_segments(image: &MemoryDump) -> Vec<(Addr, Vec<Word>)> {
+    segments(
+        image
+            .iter()
+            .enumerate()
+            .filter(|(_, word)| **word != 0)
+            .map(|(addr, word)| (addr as Addr, *word)),
+    )
+}
+
+/// Writes words out as the (comma separated, indented) body of an array.
+fn write_words<W: Write>(file: &mut W, words: &[Word], indent: &str) -> IoResult<()> {
+    words.chunks(WORDS_PER_LINE).try_for_each(|line| {
+        let line = line
+            .iter()
+            .map(|word| format!("0x{:04X},", word))
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        writeln!(file, "{}{}", indent, line)
+    })
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+/// [`ImageWriter`](super::ImageWriter) implementation for C headers that hold
+/// the image as `const uint16_t` arrays.
+pub struct CArray {
+    /// How the image is laid out.
+    pub layout: Layout,
+}
+
+impl ImageWriter for CArray {
+    const NAME: &'static str = "a C Header";
+
+    fn write<W: Write>(&self, image: &MemoryDump, file: &mut W) -> IoResult<()> {
+        writeln!(file, "/* LC-3 memory image ({} layout). */", self.layout)?;
+        writeln!(file, "#ifndef LC3_IMAGE_H")?;
+        writeln!(file, "#define LC3_IMAGE_H")?;
+        writeln!(file)?;
+        writeln!(file, "#include <stdint.h>")?;
+        writeln!(file)?;
+
+        match self.layout {
+            Layout::Full => {
+                writeln!(file, "#define IMAGE_LEN {}", ADDR_SPACE_SIZE_IN_WORDS)?;
+                writeln!(file)?;
+                writeln!(file, "static const uint16_t image[IMAGE_LEN] = {{")?;
+                write_words(file, &image[..], "    ")?;
+                writeln!(file, "}};")?;
+            }
+            Layout::Sparse => {
+                let segments = non_zero_segments(image);
+
+                for (orig, words) in segments.iter() {
+                    writeln!(file, "static const uint16_t image_x{:04X}[] = {{", orig)?;
+                    write_words(file, words, "    ")?;
+                    writeln!(file, "}};")?;
+                    writeln!(file)?;
+                }
+
+                writeln!(file, "struct image_segment {{")?;
+                writeln!(file, "    uint16_t addr;")?;
+                writeln!(file, "    uint32_t len;")?;
+                writeln!(file, "    const uint16_t *words;")?;
+                writeln!(file, "}};")?;
+                writeln!(file)?;
+                writeln!(file, "#define IMAGE_SEGMENTS_LEN {}", segments.len())?;
+                writeln!(file)?;
+
+                // C has no empty arrays (or empty initializers), so an
+                // all-zero image gets a null pointer instead.
+                if segments.is_empty() {
+                    writeln!(
+                        file,
+                        "static const struct image_segment *const image_segments = 0;"
+                    )?;
+                } else {
+                    writeln!(
+                        file,
+                        "static const struct image_segment image_segments[IMAGE_SEGMENTS_LEN] = {{"
+                    )?;
+                    segments.iter().try_for_each(|(orig, words)| {
+                        writeln!(
+                            file,
+                            "    {{ 0x{:04X}, {}, image_x{:04X} }},",
+                            orig,
+                            words.len(),
+                            orig
+                        )
+                    })?;
+                    writeln!(file, "}};")?;
+                }
+            }
+            Layout::Rle => {
+                let runs = runs(image);
+
+                writeln!(file, "struct image_run {{")?;
+                writeln!(file, "    uint16_t addr;")?;
+                writeln!(file, "    uint32_t len;")?;
+                writeln!(file, "    uint16_t word;")?;
+                writeln!(file, "}};")?;
+                writeln!(file)?;
+                writeln!(file, "#define IMAGE_RUNS_LEN {}", runs.len())?;
+                writeln!(file)?;
+                writeln!(
+                    file,
+                    "static const struct image_run image_runs[IMAGE_RUNS_LEN] = {{"
+                )?;
+                runs.iter().try_for_each(|(start, len, word)| {
+                    writeln!(file, "    {{ 0x{:04X}, {}, 0x{:04X} }},", start, len, word)
+                })?;
+                writeln!(file, "}};")?;
+            }
+        }
+
+        writeln!(file)?;
+        writeln!(file, "#endif /* LC3_IMAGE_H */")?;
+        file.flush()
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+/// [`ImageWriter`](super::ImageWriter) implementation for Rust modules that
+/// hold the image as `static` arrays.
+pub struct RustStatic {
+    /// How the image is laid out.
+    pub layout: Layout,
+}
+
+impl ImageWriter for RustStatic {
+    const NAME: &'static str = "a Rust Source File";
+
+    fn write<W: Write>(&self, image: &MemoryDump, file: &mut W) -> IoResult<()> {
+        writeln!(file, "//! LC-3 memory image ({} layout).", self.layout)?;
+        writeln!(file)?;
+
+        match self.layout {
+            Layout::Full => {
+                writeln!(file, "/// Every word in the address space.")?;
+                writeln!(
+                    file,
+                    "pub static IMAGE: [u16; {}] = [",
+                    ADDR_SPACE_SIZE_IN_WORDS
+                )?;
+                write_words(file, &image[..], "    ")?;
+                writeln!(file, "];")?;
+            }
+            Layout::Sparse => {
+                writeln!(
+                    file,
+                    "/// The non-zero segments of the image, as (address, words) pairs."
+                )?;
+                writeln!(file, "pub static SEGMENTS: &[(u16, &[u16])] = &[")?;
+                non_zero_segments(image)
+                    .iter()
+                    .try_for_each(|(orig, words)| {
+                        writeln!(file, "    (0x{:04X}, &[", orig)?;
+                        write_words(file, words, "        ")?;
+                        writeln!(file, "    ]),")
+                    })?;
+                writeln!(file, "];")?;
+            }
+            Layout::Rle => {
+                writeln!(
+                    file,
+                    "/// Runs of repeated words, as (start address, length, word) triples."
+                )?;
+                writeln!(file, "pub static RUNS: &[(u16, u32, u16)] = &[")?;
+                runs(image).iter().try_for_each(|(start, len, word)| {
+                    writeln!(file, "    (0x{:04X}, {}, 0x{:04X}),", start, len, word)
+                })?;
+                writeln!(file, "];")?;
+            }
+        }
+
+        file.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn written<I: ImageWriter>(format: I) -> String {
+        let mut image = MemoryDump::blank();
+        image[0x3000] = 0x1021;
+        image[0x3001] = 0xF025;
+        image[0x4000] = 0x0048;
+
+        let mut out = Vec::new();
+        format.write(&image, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn layouts_round_trip_through_their_names() {
+        assert_eq!(Layout::default(), Layout::Full);
+
+        for layout in Layout::ALL.iter() {
+            assert_eq!(layout.name().parse::<Layout>(), Ok(*layout));
+        }
+        assert!("dense".parse::<Layout>().is_err());
+    }
+
+    #[test]
+    fn c_sparse() {
+        let text = written(CArray {
+            layout: Layout::Sparse,
+        });
+
+        assert!(text.contains("static const uint16_t image_x3000[] = {\n    0x1021, 0xF025,\n};"));
+        assert!(text.contains("#define IMAGE_SEGMENTS_LEN 2"));
+        assert!(text.contains("    { 0x4000, 1, image_x4000 },"));
+        assert!(text.ends_with("#endif /* LC3_IMAGE_H */\n"));
+    }
+
+    #[test]
+    fn c_sparse_empty() {
+        let mut out = Vec::new();
+        CArray {
+            layout: Layout::Sparse,
+        }
+        .write(&MemoryDump::blank(), &mut out)
+        .unwrap();
+        let text = String::from_utf8(out).unwrap();
+
+        assert!(text.contains("#define IMAGE_SEGMENTS_LEN 0"));
+        assert!(text.contains("static const struct image_segment *const image_segments = 0;"));
+        assert!(!text.contains("image_x"));
+        assert!(!text.contains("[IMAGE_SEGMENTS_LEN]"));
+    }
+
+    #[test]
+    fn c_full() {
+        let text = written(CArray::default());
+        let lines = text.lines().filter(|l| l.starts_with("    0x")).count();
+
+        assert!(text.contains("static const uint16_t image[IMAGE_LEN] = {"));
+        assert_eq!(lines, ADDR_SPACE_SIZE_IN_WORDS / WORDS_PER_LINE);
+    }
+
+    #[test]
+    fn rust_rle() {
+        let text = written(RustStatic {
+            layout: Layout::Rle,
+        });
+        let runs: Vec<_> = text.lines().skip_while(|l| !l.starts_with("pub")).collect();
+
+        assert_eq!(
+            runs[..4],
+            [
+                "pub static RUNS: &[(u16, u32, u16)] = &[",
+                "    (0x0000, 12288, 0x0000),",
+                "    (0x3000, 1, 0x1021),",
+                "    (0x3001, 1, 0xF025),",
+            ]
+        );
+        assert_eq!(runs.last(), Some(&"];"));
+    }
+}
@@ -0,0 +1,220 @@
+//! [`ObjFileFormat`](super::ObjFileFormat) implementation for
+//! [Motorola S-record](https://en.wikipedia.org/wiki/SREC_(file_format)) files.
+//!
+//! See the [`byte_addressed`](super::byte_addressed) module for how LC-3 words
+//! map onto bytes.
+use super::byte_addressed::{decode_hex, encode_hex, from_bytes, insert_bytes, records, to_bytes};
+use super::{
+    text, Confidence, Loadable, Location, ObjFileFormat, ObjFileWriter, ParseError, ParseResult,
+    WriteResult,
+};
+
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use alloc::{format, vec, vec::Vec};
+use core::fmt::{self, Display};
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+/// Object File type for [`SRecord`](SRecord).
+pub struct SRecordObjFile {
+    loadables: Vec<Loadable>,
+}
+
+impl Display for SRecordObjFile {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.loadables
+            .iter()
+            .try_for_each(|(addr, word)| writeln!(fmt, "<{:04X}>: {:04X}", addr, word))
+    }
+}
+
+impl From<Vec<Loadable>> for SRecordObjFile {
+    fn from(loadables: Vec<Loadable>) -> Self {
+        SRecordObjFile { loadables }
+    }
+}
+
+impl From<SRecordObjFile> for Vec<Loadable> {
+    fn from(obj: SRecordObjFile) -> Self {
+        obj.loadables
+    }
+}
+
+/// A single decoded record: type, address, and data.
+type Record = (u8, u32, Vec<u8>);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+/// [`ObjFileFormat`](super::ObjFileFormat) implementation for
+/// [Motorola S-record](https://en.wikipedia.org/wiki/SREC_(file_format)) files.
+///
+/// Words are stored big endian at byte address `2 * addr`. Files that fit in
+/// 16-bit byte addresses are written with `S1`/`S9` records; everything else
+/// uses `S2`/`S8` records.
+pub struct SRecord;
+
+impl SRecord {
+    /// Bytes of data per record; this is what most tools use.
+    const RECORD_LEN: usize = 16;
+
+    /// Goes in the `S0` header record.
+    const HEADER: &'static [u8] = b"obj-conv";
+
+    /// Number of address bytes each record type has, if the type is valid.
+    fn addr_len(kind: u8) -> Option<usize> {
+        match kind {
+            0 | 1 | 5 | 9 => Some(2),
+            2 | 6 | 8 => Some(3),
+            3 | 7 => Some(4),
+            _ => None,
+        }
+    }
+
+    fn decode_record(line: &str) -> Result<Record, String> {
+        let mut chars = line.chars();
+        let kind = match (chars.next(), chars.next().and_then(|c| c.to_digit(10))) {
+            (Some('S'), Some(kind)) => kind as u8,
+            _ => return Err("records must start with `S` and a record type".to_string()),
+        };
+        let addr_len = SRecord::addr_len(kind).ok_or("unknown record type")?;
+
+        let bytes = decode_hex(&line[2..]).ok_or("records must be pairs of hex digits")?;
+        if bytes.len() < 2 + addr_len || bytes.len() != 1 + bytes[0] as usize {
+            return Err("record length doesn't match its byte count".to_string());
+        }
+
+        if bytes.iter().fold(0u8, |acc, b| acc.wrapping_add(*b)) != 0xFF {
+            return Err("bad checksum".to_string());
+        }
+
+        let addr = bytes[1..=addr_len]
+            .iter()
+            .fold(0u32, |acc, b| (acc << 8) | u32::from(*b));
+
+        Ok((kind, addr, bytes[1 + addr_len..bytes.len() - 1].to_vec()))
+    }
+
+    fn write_record(out: &mut String, kind: u8, addr: u32, data: &[u8]) {
+        let addr_len = SRecord::addr_len(kind).expect("a valid record type");
+
+        let mut record = vec![(addr_len + data.len() + 1) as u8];
+        record.extend_from_slice(&addr.to_be_bytes()[4 - addr_len..]);
+        record.extend_from_slice(data);
+        record.push(!record.iter().fold(0u8, |acc, b| acc.wrapping_add(*b)));
+
+        out.push_str(&format!("S{}{}\n", kind, encode_hex(&record)));
+    }
+}
+
+impl ObjFileFormat for SRecord {
+    type Parsed = Vec<Loadable>;
+    type Return = SRecordObjFile;
+    const NAME: &'static str = "a Motorola S-record File";
+
+    fn detect_bytes(bytes: &[u8]) -> Confidence {
+        let text = match text(bytes) {
+            Ok(text) => text,
+            Err(_) => return Confidence::NONE,
+        };
+
+        let mut records = text
+            .lines()
+            .map(str::trim)
+            .filter(|l| !l.is_empty())
+            .map(SRecord::decode_record);
+
+        match records.next() {
+            Some(Ok(_)) if records.all(|r| r.is_ok()) => Confidence::new(95),
+            _ => Confidence::NONE,
+        }
+    }
+
+    fn parse_bytes(file: &[u8]) -> ParseResult<Self::Return> {
+        let text = text(file)?;
+        let mut bytes = BTreeMap::new();
+
+        for (idx, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let at = Location::Line(idx + 1);
+            let (kind, addr, data) = SRecord::decode_record(line)
+                .map_err(|reason| ParseError::BadRecord { at, reason })?;
+
+            match kind {
+                1..=3 => insert_bytes(&mut bytes, addr, &data, at)?,
+                7..=9 => break,
+                // Headers and record counts:
+                _ => {}
+            }
+        }
+
+        Ok(from_bytes(&bytes)?.into())
+    }
+}
+
+impl ObjFileWriter for SRecord {
+    fn from_loadables(loadables: Vec<Loadable>) -> Self::Return {
+        loadables.into()
+    }
+
+    fn write_bytes(obj: &Self::Return) -> WriteResult<Vec<u8>> {
+        let records = records(&to_bytes(&obj.loadables), SRecord::RECORD_LEN);
+
+        let wide = records
+            .iter()
+            .any(|(addr, data)| addr + data.len() as u32 > 0x1_0000);
+        let (data_kind, end_kind) = if wide { (2, 8) } else { (1, 9) };
+
+        let mut out = String::new();
+        SRecord::write_record(&mut out, 0, 0, SRecord::HEADER);
+
+        records
+            .iter()
+            .for_each(|(addr, data)| SRecord::write_record(&mut out, data_kind, *addr, data));
+
+        if records.len() <= 0xFFFF {
+            SRecord::write_record(&mut out, 5, records.len() as u32, &[]);
+        }
+
+        SRecord::write_record(&mut out, end_kind, 0, &[]);
+
+        Ok(out.into_bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let program = vec![(0x3000, 0x1021), (0x3001, 0xF025), (0x4000, 0x0048)];
+
+        let obj = SRecord::from_loadables(program.clone());
+        let bytes = SRecord::write_bytes(&obj).unwrap();
+        let text = core::str::from_utf8(&bytes).unwrap();
+
+        assert!(text.lines().skip(1).all(|l| !l.starts_with("S2")));
+        assert!(text.lines().any(|l| l == "S10760001021F02552"));
+        assert_eq!(SRecord::parse_bytes(&bytes).unwrap(), obj);
+        assert_eq!(Vec::<Loadable>::from(obj), program);
+    }
+
+    #[test]
+    fn high_addresses_use_wider_records() {
+        let program = vec![(0x3000, 0x1021), (0xFFFF, 0xABCD)];
+
+        let obj = SRecord::from_loadables(program.clone());
+        let bytes = SRecord::write_bytes(&obj).unwrap();
+        let text = core::str::from_utf8(&bytes).unwrap();
+
+        assert!(text.lines().any(|l| l.starts_with("S2")));
+        assert!(text.lines().last().unwrap().starts_with("S8"));
+        assert_eq!(
+            Vec::<Loadable>::from(SRecord::parse_bytes(&bytes).unwrap()),
+            program
+        );
+    }
+}
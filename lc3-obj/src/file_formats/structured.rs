@@ -0,0 +1,381 @@
+//! Machine readable dumps of programs, as [JSON](Json) or [YAML](Yaml), for
+//! tools (i.e. autograders) that want to look at a program without having to
+//! parse an object file format. Dumps can be read back in, too.
+//!
+//! Both formats use the same schema:
+//! ```text
+//! {
+//!   "schema": 1,
+//!   "segments": [
+//!     {
+//!       "origin": 12288,
+//!       "words": [
+//!         {
+//!           "addr": 12288,
+//!           "word": 4129,
+//!           "instruction": "ADD R0, R0, #1",
+//!           "code": true,
+//!           "source": "ADD R0,R0,#1"
+//!         }
+//!       ]
+//!     }
+//!   ],
+//!   "symbols": { "MAIN": 12288 }
+//! }
+//! ```
+//!
+//! - Every segment is a run of contiguous words starting at `origin` (i.e.
+//!   where an `.ORIG` was).
+//! - `instruction` is the word, disassembled (`null` for words that aren't
+//!   instructions) and `code` is whether the word is reachable from an entry
+//!   point (see [`Analysis`](crate::analysis::Analysis)).
+//! - `source` is the word's source line, for programs that came from LC3Tools
+//!   object files (`null` otherwise).
+//!
+//! Addresses and words are plain numbers. When reading a dump, only `schema`,
+//! `origin`, and `word` are required; `addr` (if it's there) has to match
+//! where the word goes, `source` and `symbols` are kept, and everything else
+//! is ignored.
+use super::{
+    segments, text, Confidence, Loadable, Location, ObjFileFormat, ObjFileWriter, Object,
+    ParseError, ParseResult, WriteError, WriteResult,
+};
+use crate::analysis::Analysis;
+use crate::disasm::disassemble;
+
+use lc3_isa::{Addr, Word};
+
+use serde::{Deserialize, Serialize};
+
+use std::collections::BTreeMap;
+use std::fmt::{self, Display};
+
+/// Version of the schema that dumps are written with (and that can be read).
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// A word in a [`StructuredSegment`](StructuredSegment).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StructuredWord {
+    /// Where the word goes.
+    #[serde(default)]
+    pub addr: Option<Addr>,
+    /// The word.
+    pub word: Word,
+    /// The word, disassembled, if it's an instruction.
+    #[serde(default)]
+    pub instruction: Option<String>,
+    /// Whether the word is code.
+    #[serde(default)]
+    pub code: Option<bool>,
+    /// The source line the word was assembled from, if there is one.
+    #[serde(default)]
+    pub source: Option<String>,
+}
+
+/// A run of contiguous words in a [`StructuredObjFile`](StructuredObjFile).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StructuredSegment {
+    /// Where the first word goes.
+    pub origin: Addr,
+    /// The words in the segment.
+    pub words: Vec<StructuredWord>,
+}
+
+/// Object File type for [`Json`](Json) and [`Yaml`](Yaml).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StructuredObjFile {
+    /// The version of the schema the dump uses.
+    pub schema: u32,
+    /// The segments in the program.
+    pub segments: Vec<StructuredSegment>,
+    /// Labels for addresses in the program.
+    #[serde(default)]
+    pub symbols: BTreeMap<String, Addr>,
+}
+
+impl StructuredObjFile {
+    /// Describes a program; the analysis says which of its words are code.
+    pub fn new(object: &Object, analysis: &Analysis) -> Self {
+        let segments = segments(object.loadables.iter().copied())
+            .into_iter()
+            .map(|(origin, words)| StructuredSegment {
+                origin,
+                words: words
+                    .into_iter()
+                    .enumerate()
+                    .map(|(idx, word)| {
+                        let addr = origin.wrapping_add(idx as Addr);
+
+                        StructuredWord {
+                            addr: Some(addr),
+                            word,
+                            instruction: disassemble(addr, word),
+                            code: Some(analysis.is_code(addr)),
+                            source: object
+                                .source_lines
+                                .get(&addr)
+                                .map(|l| l.text.clone())
+                                .filter(|l| !l.is_empty()),
+                        }
+                    })
+                    .collect(),
+            })
+            .collect();
+
+        StructuredObjFile {
+            schema: SCHEMA_VERSION,
+            segments,
+            symbols: object
+                .symbols
+                .iter()
+                .map(|(label, addr)| (label.to_string(), addr))
+                .collect(),
+        }
+    }
+
+    /// Pairs every word with its address.
+    pub(crate) fn placed_words(&self) -> impl Iterator<Item = (Addr, &StructuredWord)> + '_ {
+        self.segments.iter().flat_map(|s| {
+            s.words
+                .iter()
+                .enumerate()
+                .map(move |(idx, w)| (s.origin.wrapping_add(idx as Addr), w))
+        })
+    }
+
+    /// Checks that the dump uses a schema we know and that its words all go
+    /// where they say they go.
+    fn validate(self) -> ParseResult<Self> {
+        if self.schema != SCHEMA_VERSION {
+            return Err(ParseError::Malformed {
+                reason: format!(
+                    "unsupported schema version {} (expected {})",
+                    self.schema, SCHEMA_VERSION
+                ),
+            });
+        }
+
+        for (segment, s) in self.segments.iter().enumerate() {
+            for (word, w) in s.words.iter().enumerate() {
+                let at = Location::Word { segment, word };
+                let addr = u32::from(s.origin) + word as u32;
+
+                if addr > u32::from(Addr::MAX) {
+                    return Err(ParseError::AddressOverflow { at });
+                }
+
+                if let Some(a) = w.addr.filter(|a| u32::from(*a) != addr) {
+                    return Err(ParseError::BadRecord {
+                        at,
+                        reason: format!("`addr` is x{:04X} but the word goes at x{:04X}", a, addr),
+                    });
+                }
+            }
+        }
+
+        Ok(self)
+    }
+}
+
+impl Display for StructuredObjFile {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.segments.iter().try_for_each(|s| {
+            writeln!(fmt, "<segment @ {:04X}>", s.origin)?;
+
+            s.words.iter().enumerate().try_for_each(|(idx, w)| {
+                let addr = s.origin.wrapping_add(idx as Addr);
+                write!(fmt, "  {:04X}: {:04X}", addr, w.word)?;
+
+                if let Some(source) = &w.source {
+                    write!(fmt, "  ; {}", source)?;
+                }
+
+                writeln!(fmt)
+            })
+        })
+    }
+}
+
+impl From<StructuredObjFile> for Vec<Loadable> {
+    fn from(obj: StructuredObjFile) -> Self {
+        obj.placed_words().map(|(a, w)| (a, w.word)).collect()
+    }
+}
+
+/// Builds a dump from loadables, analyzing them from their segment origins.
+fn from_loadables(loadables: Vec<Loadable>) -> StructuredObjFile {
+    let analysis = Analysis::run(&loadables, &[]);
+    StructuredObjFile::new(&loadables.into(), &analysis)
+}
+
+/// Wraps a serializer's error.
+fn unserializable<E: Display>(err: E) -> WriteError {
+    WriteError::Unserializable {
+        reason: err.to_string(),
+    }
+}
+
+/// [`ObjFileFormat`](super::ObjFileFormat) implementation for JSON dumps.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Json;
+
+impl ObjFileFormat for Json {
+    type Parsed = Vec<Loadable>;
+    type Return = StructuredObjFile;
+    const NAME: &'static str = "a JSON Object Dump";
+
+    fn detect_bytes(bytes: &[u8]) -> Confidence {
+        let text = match text(bytes) {
+            Ok(text) => text,
+            Err(_) => return Confidence::NONE,
+        };
+
+        if serde_json::from_str::<StructuredObjFile>(text).is_ok() {
+            Confidence::CERTAIN
+        } else if text.trim_start().starts_with('{') {
+            Confidence::new(50)
+        } else {
+            Confidence::NONE
+        }
+    }
+
+    fn parse_bytes(bytes: &[u8]) -> ParseResult<Self::Return> {
+        serde_json::from_str::<StructuredObjFile>(text(bytes)?)
+            .map_err(|err| ParseError::Malformed {
+                reason: err.to_string(),
+            })?
+            .validate()
+    }
+}
+
+impl ObjFileWriter for Json {
+    fn from_loadables(loadables: Vec<Loadable>) -> Self::Return {
+        from_loadables(loadables)
+    }
+
+    fn write_bytes(obj: &Self::Return) -> WriteResult<Vec<u8>> {
+        let mut bytes = serde_json::to_vec_pretty(obj).map_err(unserializable)?;
+        bytes.push(b'\n');
+
+        Ok(bytes)
+    }
+}
+
+/// [`ObjFileFormat`](super::ObjFileFormat) implementation for YAML dumps.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Yaml;
+
+impl ObjFileFormat for Yaml {
+    type Parsed = Vec<Loadable>;
+    type Return = StructuredObjFile;
+    const NAME: &'static str = "a YAML Object Dump";
+
+    /// JSON is also YAML, so this is never quite certain; anything that looks
+    /// like JSON is left to [`Json`](Json) so that JSON dumps aren't
+    /// ambiguous.
+    fn detect_bytes(bytes: &[u8]) -> Confidence {
+        if Json::detect_bytes(bytes).is_match() {
+            return Confidence::NONE;
+        }
+
+        match text(bytes).map(serde_yaml::from_str::<StructuredObjFile>) {
+            Ok(Ok(_)) => Confidence::new(90),
+            _ => Confidence::NONE,
+        }
+    }
+
+    fn parse_bytes(bytes: &[u8]) -> ParseResult<Self::Return> {
+        serde_yaml::from_str::<StructuredObjFile>(text(bytes)?)
+            .map_err(|err| ParseError::Malformed {
+                reason: err.to_string(),
+            })?
+            .validate()
+    }
+}
+
+impl ObjFileWriter for Yaml {
+    fn from_loadables(loadables: Vec<Loadable>) -> Self::Return {
+        from_loadables(loadables)
+    }
+
+    fn write_bytes(obj: &Self::Return) -> WriteResult<Vec<u8>> {
+        let mut bytes = serde_yaml::to_vec(obj).map_err(unserializable)?;
+        bytes.push(b'\n');
+
+        Ok(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn program() -> Vec<Loadable> {
+        vec![(0x3000, 0x1021), (0x3001, 0xF025), (0x4000, 0x0048)]
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let obj = Json::from_loadables(program());
+        let bytes = Json::write_bytes(&obj).unwrap();
+
+        assert_eq!(obj.segments.len(), 2);
+        assert_eq!(Json::detect_bytes(&bytes), Confidence::CERTAIN);
+        assert_eq!(Json::parse_bytes(&bytes).unwrap(), obj);
+        assert_eq!(Vec::<Loadable>::from(obj), program());
+    }
+
+    #[test]
+    fn yaml_round_trip() {
+        let obj = Yaml::from_loadables(program());
+        let bytes = Yaml::write_bytes(&obj).unwrap();
+
+        assert!(Yaml::detect_bytes(&bytes).is_match());
+        assert_eq!(Json::detect_bytes(&bytes), Confidence::NONE);
+        assert_eq!(Yaml::parse_bytes(&bytes).unwrap(), obj);
+    }
+
+    #[test]
+    fn only_origins_and_words_are_required() {
+        let json =
+            br#"{ "schema": 1, "segments": [{ "origin": 12288, "words": [{ "word": 4129 }] }] }"#;
+
+        let obj = Json::parse_bytes(json).unwrap();
+        assert_eq!(Vec::<Loadable>::from(obj), vec![(0x3000, 0x1021)]);
+    }
+
+    #[test]
+    fn bad_dumps() {
+        let wrong_addr = br#"{ "schema": 1, "segments": [
+            { "origin": 12288, "words": [{ "addr": 1, "word": 0 }] }
+        ] }"#;
+        let wrong_schema = br#"{ "schema": 2, "segments": [] }"#;
+        let overflow = br#"{ "schema": 1, "segments": [
+            { "origin": 65535, "words": [{ "word": 0 }, { "word": 0 }] }
+        ] }"#;
+
+        assert!(matches!(
+            Json::parse_bytes(wrong_addr),
+            Err(ParseError::BadRecord {
+                at: Location::Word {
+                    segment: 0,
+                    word: 0
+                },
+                ..
+            })
+        ));
+        assert!(matches!(
+            Json::parse_bytes(wrong_schema),
+            Err(ParseError::Malformed { .. })
+        ));
+        assert!(matches!(
+            Json::parse_bytes(overflow),
+            Err(ParseError::AddressOverflow {
+                at: Location::Word {
+                    segment: 0,
+                    word: 1
+                }
+            })
+        ));
+    }
+}
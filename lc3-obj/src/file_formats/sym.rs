@@ -0,0 +1,160 @@
+//! Symbol tables, as written by the classic Patt & Patel `lc3as` assembler
+//! (`.sym` files).
+//!
+//! These look like this (the gap after each `//` is a tab):
+//! ```text
+//! // Symbol table
+//! // Scope level 0:
+//! //  Symbol Name       Page Address
+//! //  ----------------  ------------
+//! //  LOOP              3002
+//! ```
+use super::IoResult;
+
+use lc3_isa::Addr;
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{Read, Write};
+
+/// Label to address mappings.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SymbolTable {
+    symbols: BTreeMap<String, Addr>,
+    /// The same labels, by address (for looking up labels while printing).
+    labels: BTreeMap<Addr, BTreeSet<String>>,
+}
+
+impl SymbolTable {
+    /// Parses an `lc3as` symbol table.
+    ///
+    /// Lines that aren't a label followed by an address (i.e. the header) are
+    /// skipped.
+    pub fn parse<R: Read>(file: &mut R) -> IoResult<Self> {
+        let mut text = String::new();
+        let _ = file.read_to_string(&mut text)?;
+
+        let mut table = SymbolTable::default();
+        text.lines()
+            .map(|l| l.trim().trim_start_matches("//"))
+            .filter_map(
+                |l| match l.split_whitespace().collect::<Vec<_>>().as_slice() {
+                    [label, addr] if addr.len() == 4 => Addr::from_str_radix(addr, 16)
+                        .ok()
+                        .map(|addr| (label.to_string(), addr)),
+                    _ => None,
+                },
+            )
+            .for_each(|(label, addr)| table.insert(&label, addr));
+
+        Ok(table)
+    }
+
+    /// Writes the symbol table out in the format `lc3as` uses.
+    pub fn write<W: Write>(&self, file: &mut W) -> IoResult<()> {
+        writeln!(file, "// Symbol table")?;
+        writeln!(file, "// Scope level 0:")?;
+        writeln!(file, "//\tSymbol Name       Page Address")?;
+        writeln!(file, "//\t----------------  ------------")?;
+
+        let mut symbols: Vec<_> = self.symbols.iter().collect();
+        symbols.sort_by_key(|(label, addr)| (*addr, *label));
+
+        symbols
+            .iter()
+            .try_for_each(|(label, addr)| writeln!(file, "//\t{:<16}  {:04X}", label, addr))?;
+
+        writeln!(file)?;
+        file.flush()
+    }
+
+    /// Adds a label, replacing any existing label with the same name.
+    pub fn insert(&mut self, label: &str, addr: Addr) {
+        if let Some(old) = self.symbols.insert(label.to_string(), addr) {
+            let labels = self
+                .labels
+                .get_mut(&old)
+                .expect("every label is by address too");
+            let _ = labels.remove(label);
+
+            if labels.is_empty() {
+                let _ = self.labels.remove(&old);
+            }
+        }
+
+        let _ = self
+            .labels
+            .entry(addr)
+            .or_default()
+            .insert(label.to_string());
+    }
+
+    /// Adds all the labels in another symbol table, replacing any existing
+    /// labels with the same names.
+    pub fn merge(&mut self, other: SymbolTable) {
+        other
+            .symbols
+            .iter()
+            .for_each(|(label, addr)| self.insert(label, *addr));
+    }
+
+    /// Returns a label for the given address, if there is one.
+    ///
+    /// If multiple labels point to the same address, the one that comes first
+    /// alphabetically is returned.
+    pub fn label_for(&self, addr: Addr) -> Option<&str> {
+        self.labels
+            .get(&addr)
+            .and_then(|labels| labels.iter().next())
+            .map(String::as_str)
+    }
+
+    /// Iterates over the labels and their addresses, sorted by label.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, Addr)> + '_ {
+        self.symbols
+            .iter()
+            .map(|(label, addr)| (label.as_str(), *addr))
+    }
+
+    /// Whether there are any labels at all.
+    pub fn is_empty(&self) -> bool {
+        self.symbols.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let mut symbols = SymbolTable::default();
+        symbols.insert("LOOP", 0x3002);
+        symbols.insert("START", 0x3000);
+
+        let mut written = Vec::new();
+        symbols.write(&mut written).unwrap();
+
+        let text = String::from_utf8(written.clone()).unwrap();
+        assert_eq!(text.lines().nth(4), Some("//\tSTART             3000"));
+        assert_eq!(SymbolTable::parse(&mut &written[..]).unwrap(), symbols);
+    }
+
+    #[test]
+    fn labels_for_addresses() {
+        let mut symbols = SymbolTable::default();
+        symbols.insert("LOOP", 0x3002);
+        symbols.insert("AGAIN", 0x3002);
+        assert_eq!(symbols.label_for(0x3002), Some("AGAIN"));
+
+        // Moving a label takes it off of its old address:
+        symbols.insert("AGAIN", 0x3005);
+        assert_eq!(symbols.label_for(0x3002), Some("LOOP"));
+        assert_eq!(symbols.label_for(0x3005), Some("AGAIN"));
+
+        let mut other = SymbolTable::default();
+        other.insert("LOOP", 0x3000);
+        symbols.merge(other);
+        assert_eq!(symbols.label_for(0x3000), Some("LOOP"));
+        assert_eq!(symbols.label_for(0x3002), None);
+    }
+}
@@ -0,0 +1,59 @@
+//! Building memory images out of programs.
+use super::Loadable;
+
+use lc3_isa::util::MemoryDump;
+use lc3_os::OS_IMAGE;
+use lc3_shims::memory::FileBackedMemoryShim;
+
+use std::io::{Error, ErrorKind, Result as IoResult};
+
+/// What a memory image starts out with, before a program is put on top.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OsStrategy<'a> {
+    /// The stock OS.
+    Default,
+    /// The OS in the memory dump at the given path.
+    Custom(&'a str),
+    /// No OS; just zeros.
+    None,
+}
+
+impl<'a> OsStrategy<'a> {
+    /// No OS if `with_os` is false, otherwise the OS at `custom_path` if
+    /// there is one or the stock OS if there isn't.
+    pub fn new(with_os: bool, custom_path: Option<&'a str>) -> Self {
+        use OsStrategy::*;
+
+        match (with_os, custom_path) {
+            (false, _) => None,
+            (true, Option::None) => Default,
+            (true, Some(path)) => Custom(path),
+        }
+    }
+
+    /// Makes a memory image with just the OS in it.
+    pub fn make_memory_dump(self) -> IoResult<MemoryDump> {
+        use OsStrategy::*;
+
+        Ok(match self {
+            Default => OS_IMAGE.clone(),
+            Custom(ref path) => FileBackedMemoryShim::from_existing_file(path)
+                .map_err(|_| {
+                    Error::new(
+                        ErrorKind::InvalidData,
+                        format!("`{}` isn't a valid OS image.", path),
+                    )
+                })?
+                .into(),
+            None => MemoryDump::blank(),
+        })
+    }
+}
+
+/// Makes a memory image with the program layered on top of the OS.
+pub fn build_image(loadables: Vec<Loadable>, os: OsStrategy<'_>) -> IoResult<MemoryDump> {
+    let mut image = os.make_memory_dump()?;
+    let _ = image.layer_loadable(loadables);
+
+    Ok(image)
+}
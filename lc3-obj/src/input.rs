@@ -0,0 +1,170 @@
+//! Reading files in a given format into [programs](Object) (or, for
+//! relocatable object files, into sections that still have to be
+//! [linked](crate::linker)).
+use super::file_formats::reloc::RelocatableObjFile;
+use super::file_formats::{
+    Format, IntelHex, Json, Lc3AsBin, Lc3AsHex, Lc3Tools, Lumetta, MemDump, ObjFileFormat, Object,
+    Relocatable, SRecord, Yaml,
+};
+
+use std::fs;
+use std::io::{Error, ErrorKind, Result as IoResult};
+use std::path::Path;
+
+/// How input files are read.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ReadOptions {
+    /// Keep what can be parsed from damaged files instead of failing.
+    pub lenient: bool,
+    /// Keep a human readable listing of the file, as parsed.
+    pub listing: bool,
+}
+
+/// A parsed input: either a program, or sections that still have to be linked.
+#[derive(Debug)]
+pub enum Input {
+    /// A program.
+    Program(Object),
+    /// Sections that have to be linked before they can go anywhere.
+    Relocatable(RelocatableObjFile),
+}
+
+/// An input, along with its listing (if one was asked for).
+#[derive(Debug)]
+pub struct Parsed<T> {
+    /// What was read.
+    pub input: T,
+    /// The file, as parsed, in the format's own terms.
+    pub listing: Option<String>,
+    /// Anything worth warning about that came up while parsing the file.
+    pub warnings: Vec<String>,
+}
+
+impl<T> Parsed<T> {
+    fn map<U>(self, func: impl FnOnce(T) -> U) -> Parsed<U> {
+        Parsed {
+            input: func(self.input),
+            listing: self.listing,
+            warnings: self.warnings,
+        }
+    }
+}
+
+/// Reads the contents of a file as a particular format, making sure it looks
+/// like that format first. `name` is what to call the file in errors (i.e. its
+/// path).
+pub fn try_format<F: ObjFileFormat>(
+    bytes: &[u8],
+    name: &str,
+    opts: ReadOptions,
+) -> IoResult<Parsed<Object>>
+where
+    F::Return: Into<Object>,
+{
+    if !F::detect_bytes(bytes).is_match() {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            format!("`{}` doesn't look like {}.", name, F::NAME),
+        ));
+    }
+
+    let returned = if opts.lenient {
+        F::parse_bytes_lenient(bytes)
+    } else {
+        F::parse_bytes(bytes)
+    };
+
+    let returned = returned.map_err(|err| {
+        Error::new(
+            ErrorKind::InvalidData,
+            format!("`{}` isn't valid as {}: {}.", name, F::NAME, err),
+        )
+    })?;
+
+    Ok(Parsed {
+        listing: if opts.listing {
+            Some(returned.to_string())
+        } else {
+            None
+        },
+        warnings: F::warnings(&returned),
+        input: returned.into(),
+    })
+}
+
+/// Like [`try_format`](try_format), for relocatable object files.
+pub fn try_relocatable(
+    bytes: &[u8],
+    name: &str,
+    opts: ReadOptions,
+) -> IoResult<Parsed<RelocatableObjFile>> {
+    if !Relocatable::detect_bytes(bytes).is_match() {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            format!("`{}` doesn't look like {}.", name, Relocatable::NAME),
+        ));
+    }
+
+    let returned = Relocatable::parse_bytes(bytes).map_err(|err| {
+        Error::new(
+            ErrorKind::InvalidData,
+            format!("`{}` isn't valid as {}: {}.", name, Relocatable::NAME, err),
+        )
+    })?;
+
+    Ok(Parsed {
+        listing: if opts.listing {
+            Some(returned.to_string())
+        } else {
+            None
+        },
+        warnings: Vec::new(),
+        input: returned,
+    })
+}
+
+/// Reads the contents of a file as the given format. Fails for formats that
+/// can only be written.
+pub fn read_as(
+    bytes: &[u8],
+    name: &str,
+    format: Format,
+    opts: ReadOptions,
+) -> IoResult<Parsed<Input>> {
+    let parsed = match format {
+        Format::Lc3Tools => try_format::<Lc3Tools<'_>>(bytes, name, opts),
+        Format::Lumetta => try_format::<&Lumetta>(bytes, name, opts),
+        Format::MemoryDump => try_format::<MemDump>(bytes, name, opts),
+        Format::IntelHex => try_format::<IntelHex>(bytes, name, opts),
+        Format::SRecord => try_format::<SRecord>(bytes, name, opts),
+        Format::Lc3AsHex => try_format::<Lc3AsHex>(bytes, name, opts),
+        Format::Lc3AsBin => try_format::<Lc3AsBin>(bytes, name, opts),
+        Format::Relocatable => {
+            return try_relocatable(bytes, name, opts).map(|p| p.map(Input::Relocatable))
+        }
+        Format::Json => try_format::<Json>(bytes, name, opts),
+        Format::Yaml => try_format::<Yaml>(bytes, name, opts),
+        Format::ReadMemH
+        | Format::Coe
+        | Format::Mif
+        | Format::CArray
+        | Format::RustStatic
+        | Format::Asm
+        | Format::Dot => Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!("Reading {} isn't supported.", format.description()),
+        )),
+    };
+
+    parsed.map(|p| p.map(Input::Program))
+}
+
+/// Reads the file at the given path as the given format.
+pub fn read_path<P: AsRef<Path>>(
+    path: P,
+    format: Format,
+    opts: ReadOptions,
+) -> IoResult<Parsed<Input>> {
+    let name = path.as_ref().display().to_string();
+    read_as(&fs::read(path)?, &name, format, opts)
+}
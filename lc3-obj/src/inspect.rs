@@ -0,0 +1,439 @@
+//! Summarizing what's in a program: where its segments are, what kinds of
+//! instructions it uses, which TRAPs it calls, and whether it uses the memory
+//! mapped device registers.
+use super::analysis::{pc_relative_target, Analysis};
+use super::disasm::trap_alias;
+use super::file_formats::{segments, Format, Object};
+use super::validation::{range, DEVICE_REGISTERS};
+
+use lc3_isa::{Addr, Instruction, Word};
+
+use serde::Serialize;
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::convert::TryFrom;
+use std::fmt::{self, Display};
+
+/// A contiguous run of words.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Segment {
+    /// Where the segment starts.
+    pub origin: Addr,
+    /// How many words are in the segment.
+    pub length: usize,
+}
+
+/// A TRAP routine that a program calls.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TrapUse {
+    /// The TRAP vector.
+    pub vector: u8,
+    /// The routine's assembler alias, if it has one.
+    pub name: Option<&'static str>,
+    /// How many `TRAP` instructions use the vector.
+    pub count: usize,
+}
+
+/// A memory mapped device register that a program uses.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DeviceRegister {
+    /// The register's address.
+    pub addr: Addr,
+    /// The register's usual name, if it's one of the standard ones.
+    pub name: Option<&'static str>,
+}
+
+/// Everything [`inspect`](Info::new) found out about a program.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Info {
+    /// The file the program came from.
+    pub path: String,
+    /// Short name of the format the file is in.
+    pub format: &'static str,
+    /// Version of the encoding used for the file, for formats that have one.
+    pub version: Option<u16>,
+    /// The segments in the program, in the order they appear in the file.
+    pub segments: Vec<Segment>,
+    /// How many words there are in total.
+    pub words: usize,
+    /// Where the program starts (the origin of its first segment).
+    pub entry: Option<Addr>,
+    /// How many words are code (reachable from an entry point).
+    pub code_words: usize,
+    /// How many instructions of each kind there are in the code, by opcode.
+    pub instruction_mix: BTreeMap<&'static str, usize>,
+    /// The TRAP routines the code calls.
+    pub trap_vectors: Vec<TrapUse>,
+    /// Device registers that the code accesses (through pointers in the
+    /// program) or that the program has words for.
+    pub device_registers: Vec<DeviceRegister>,
+}
+
+/// The mnemonic for an instruction's opcode; instructions that share an opcode
+/// (`JMP` and `RET`, `JSR` and `JSRR`, and the two forms of `ADD` and `AND`)
+/// share a mnemonic.
+fn opcode(insn: Instruction) -> &'static str {
+    use Instruction::*;
+
+    match insn {
+        AddReg { .. } | AddImm { .. } => "ADD",
+        AndReg { .. } | AndImm { .. } => "AND",
+        Br { .. } => "BR",
+        Jmp { .. } | Ret => "JMP",
+        Jsr { .. } | Jsrr { .. } => "JSR",
+        Ld { .. } => "LD",
+        Ldi { .. } => "LDI",
+        Ldr { .. } => "LDR",
+        Lea { .. } => "LEA",
+        Not { .. } => "NOT",
+        Rti => "RTI",
+        St { .. } => "ST",
+        Sti { .. } => "STI",
+        Str { .. } => "STR",
+        Trap { .. } => "TRAP",
+    }
+}
+
+/// Names of the standard device registers.
+fn device_register_name(addr: Addr) -> Option<&'static str> {
+    match addr {
+        0xFE00 => Some("KBSR"),
+        0xFE02 => Some("KBDR"),
+        0xFE04 => Some("DSR"),
+        0xFE06 => Some("DDR"),
+        0xFFFE => Some("MCR"),
+        _ => None,
+    }
+}
+
+impl Info {
+    /// Looks over a program; the analysis says which of its words are code.
+    pub fn new(
+        path: &str,
+        format: Format,
+        version: Option<u16>,
+        object: &Object,
+        analysis: &Analysis,
+    ) -> Self {
+        let words: BTreeMap<Addr, Word> = object.loadables.iter().copied().collect();
+        let code: Vec<(Addr, Instruction)> = words
+            .iter()
+            .filter(|(a, _)| analysis.is_code(**a))
+            .filter_map(|(a, w)| Instruction::try_from(*w).ok().map(|i| (*a, i)))
+            .collect();
+
+        let mut instruction_mix = BTreeMap::new();
+        let mut traps: BTreeMap<u8, usize> = BTreeMap::new();
+        let mut devices: BTreeSet<Addr> = words
+            .keys()
+            .copied()
+            .filter(|a| DEVICE_REGISTERS.contains(a))
+            .collect();
+
+        for (addr, insn) in code.iter().copied() {
+            *instruction_mix.entry(opcode(insn)).or_default() += 1;
+
+            match insn {
+                Instruction::Trap { trapvec } => *traps.entry(trapvec).or_default() += 1,
+                // Device registers are out of reach of PC-relative offsets
+                // from user space so they're accessed through pointers:
+                Instruction::Ldi { .. } | Instruction::Sti { .. } => {
+                    let pointer = pc_relative_target(addr, insn)
+                        .and_then(|p| words.get(&p))
+                        .filter(|p| DEVICE_REGISTERS.contains(p));
+
+                    if let Some(reg) = pointer {
+                        let _ = devices.insert(*reg);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Info {
+            path: path.to_string(),
+            format: format.name(),
+            version,
+            segments: segments(object.loadables.iter().copied())
+                .into_iter()
+                .map(|(origin, words)| Segment {
+                    origin,
+                    length: words.len(),
+                })
+                .collect(),
+            words: words.len(),
+            entry: object.loadables.first().map(|(a, _)| *a),
+            code_words: code.len(),
+            instruction_mix,
+            trap_vectors: traps
+                .into_iter()
+                .map(|(vector, count)| TrapUse {
+                    vector,
+                    name: trap_alias(vector),
+                    count,
+                })
+                .collect(),
+            device_registers: devices
+                .into_iter()
+                .map(|addr| DeviceRegister {
+                    addr,
+                    name: device_register_name(addr),
+                })
+                .collect(),
+        }
+    }
+}
+
+impl Display for Info {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let plural =
+            |n: usize, what: &str| format!("{} {}{}", n, what, if n == 1 { "" } else { "s" });
+
+        writeln!(fmt, "{:<18}{}", "File:", self.path)?;
+        match self.version {
+            Some(v) => writeln!(fmt, "{:<18}{} (version {:#06X})", "Format:", self.format, v)?,
+            None => writeln!(fmt, "{:<18}{}", "Format:", self.format)?,
+        }
+
+        writeln!(
+            fmt,
+            "{:<18}{} ({})",
+            "Segments:",
+            self.segments.len(),
+            plural(self.words, "word")
+        )?;
+        self.segments.iter().try_for_each(|s| {
+            let end = s.origin.wrapping_add(s.length.saturating_sub(1) as Addr);
+            writeln!(
+                fmt,
+                "  {:<16}{}",
+                range(&(s.origin..=end)),
+                plural(s.length, "word")
+            )
+        })?;
+
+        match self.entry {
+            Some(entry) => writeln!(fmt, "{:<18}x{:04X}", "Entry point:", entry)?,
+            None => writeln!(fmt, "{:<18}none", "Entry point:")?,
+        }
+        writeln!(
+            fmt,
+            "{:<18}{} of code, {} of data",
+            "Words:",
+            plural(self.code_words, "word"),
+            plural(self.words - self.code_words, "word")
+        )?;
+
+        if self.instruction_mix.is_empty() {
+            writeln!(fmt, "{:<18}none", "Instruction mix:")?;
+        } else {
+            writeln!(fmt, "Instruction mix:")?;
+        }
+        let mut mix: Vec<_> = self.instruction_mix.iter().collect();
+        mix.sort_by_key(|(op, count)| (std::cmp::Reverse(**count), **op));
+        mix.iter().try_for_each(|(op, count)| {
+            let share = **count as f64 * 100.0 / self.code_words as f64;
+            writeln!(fmt, "  {:<16}{:>5}  {:>5.1}%", op, count, share)
+        })?;
+
+        if self.trap_vectors.is_empty() {
+            writeln!(fmt, "{:<18}none", "TRAP vectors:")?;
+        } else {
+            writeln!(fmt, "TRAP vectors:")?;
+        }
+        self.trap_vectors.iter().try_for_each(|t| {
+            let name = format!("x{:02X} {}", t.vector, t.name.unwrap_or_default());
+            writeln!(fmt, "  {:<16}{:>5}", name.trim_end(), t.count)
+        })?;
+
+        write!(fmt, "{:<18}", "Device registers:")?;
+        if self.device_registers.is_empty() {
+            writeln!(fmt, "none")
+        } else {
+            let regs: Vec<String> = self
+                .device_registers
+                .iter()
+                .map(|d| match d.name {
+                    Some(name) => format!("x{:04X} ({})", d.addr, name),
+                    None => format!("x{:04X}", d.addr),
+                })
+                .collect();
+
+            writeln!(fmt, "{}", regs.join(", "))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn inspect(loadables: Vec<(Addr, Word)>, format: Format, version: Option<u16>) -> Info {
+        let object = Object::from(loadables);
+        let analysis = Analysis::run(&object.loadables, &[]);
+
+        Info::new("prog.obj", format, version, &object, &analysis)
+    }
+
+    fn program() -> Vec<(Addr, Word)> {
+        let mut loadables: Vec<(Addr, Word)> = (0x3000..)
+            .zip(vec![
+                0xA003, // LDI R0, KBSR_PTR
+                0xF021, // OUT
+                0xF021, // OUT
+                0xF025, // HALT
+                0xFE00, // KBSR_PTR: .FILL xFE00
+            ])
+            .collect();
+        loadables.extend(vec![(0x4000, 0x1021), (0x4001, 0xC1C0)]); // ADD; RET
+        loadables.push((0xFE04, 0x0000)); // a word on the device register page
+
+        loadables
+    }
+
+    #[test]
+    fn summary() {
+        let info = inspect(program(), Format::Lc3Tools, Some(0x0101));
+        assert_eq!(
+            info.segments,
+            [
+                Segment {
+                    origin: 0x3000,
+                    length: 5
+                },
+                Segment {
+                    origin: 0x4000,
+                    length: 2
+                },
+                Segment {
+                    origin: 0xFE04,
+                    length: 1
+                },
+            ]
+        );
+        assert_eq!(info.entry, Some(0x3000));
+        assert_eq!((info.words, info.code_words), (8, 6));
+        assert_eq!(
+            info.instruction_mix.into_iter().collect::<Vec<_>>(),
+            [("ADD", 1), ("JMP", 1), ("LDI", 1), ("TRAP", 3)]
+        );
+        assert_eq!(
+            info.trap_vectors,
+            [
+                TrapUse {
+                    vector: 0x21,
+                    name: Some("OUT"),
+                    count: 2
+                },
+                TrapUse {
+                    vector: 0x25,
+                    name: Some("HALT"),
+                    count: 1
+                },
+            ]
+        );
+        assert_eq!(
+            info.device_registers,
+            [
+                DeviceRegister {
+                    addr: 0xFE00,
+                    name: Some("KBSR")
+                },
+                DeviceRegister {
+                    addr: 0xFE04,
+                    name: Some("DSR")
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn table() {
+        let info = inspect(program(), Format::Lc3Tools, Some(0x0101));
+        assert_eq!(
+            info.to_string().lines().collect::<Vec<_>>(),
+            [
+                "File:             prog.obj",
+                "Format:           lc3tools (version 0x0101)",
+                "Segments:         3 (8 words)",
+                "  x3000-x3004     5 words",
+                "  x4000-x4001     2 words",
+                "  xFE04           1 word",
+                "Entry point:      x3000",
+                "Words:            6 words of code, 2 words of data",
+                "Instruction mix:",
+                "  TRAP                3   50.0%",
+                "  ADD                 1   16.7%",
+                "  JMP                 1   16.7%",
+                "  LDI                 1   16.7%",
+                "TRAP vectors:",
+                "  x21 OUT             2",
+                "  x25 HALT            1",
+                "Device registers: xFE00 (KBSR), xFE04 (DSR)",
+            ]
+        );
+    }
+
+    #[test]
+    fn empty_programs() {
+        let info = inspect(vec![], Format::IntelHex, None);
+
+        assert!(info.segments.is_empty());
+        assert_eq!(info.entry, None);
+        assert_eq!(
+            info.to_string().lines().collect::<Vec<_>>(),
+            [
+                "File:             prog.obj",
+                "Format:           ihex",
+                "Segments:         0 (0 words)",
+                "Entry point:      none",
+                "Words:            0 words of code, 0 words of data",
+                "Instruction mix:  none",
+                "TRAP vectors:     none",
+                "Device registers: none",
+            ]
+        );
+    }
+
+    /// The field names are what scripts that use `inspect --json` rely on.
+    #[test]
+    fn json() {
+        let loadables = (0x3000..).zip(vec![0xA001, 0xF025, 0xFE00]).collect();
+        let info = inspect(loadables, Format::IntelHex, None);
+
+        assert_eq!(
+            serde_json::to_string_pretty(&info).unwrap(),
+            r#"{
+  "path": "prog.obj",
+  "format": "ihex",
+  "version": null,
+  "segments": [
+    {
+      "origin": 12288,
+      "length": 3
+    }
+  ],
+  "words": 3,
+  "entry": 12288,
+  "code_words": 2,
+  "instruction_mix": {
+    "LDI": 1,
+    "TRAP": 1
+  },
+  "trap_vectors": [
+    {
+      "vector": 37,
+      "name": "HALT",
+      "count": 1
+    }
+  ],
+  "device_registers": [
+    {
+      "addr": 65024,
+      "name": "KBSR"
+    }
+  ]
+}"#
+        );
+    }
+}
@@ -0,0 +1,177 @@
+//! Layering several inputs on top of each other to make one program, keeping
+//! track of which input each word came from.
+use super::file_formats::Object;
+use super::validation::{range, ranges};
+
+use lc3_isa::Addr;
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{self, Display};
+use std::ops::RangeInclusive;
+
+/// A range of addresses that more than one input has words for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Conflict {
+    /// The addresses both inputs have words for.
+    pub range: RangeInclusive<Addr>,
+    /// The input whose words were replaced.
+    pub replaced: String,
+    /// The input whose words were kept.
+    pub kept: String,
+}
+
+impl Display for Conflict {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            fmt,
+            "{} is claimed by both `{}` and `{}`; using `{}`",
+            range(&self.range),
+            self.replaced,
+            self.kept,
+            self.kept
+        )
+    }
+}
+
+/// Inputs layered on top of each other, in order: words from later inputs
+/// replace words from earlier inputs at the same address.
+#[derive(Debug, Clone, Default)]
+pub struct Layers {
+    object: Object,
+    names: Vec<String>,
+    owners: BTreeMap<Addr, usize>,
+    conflicts: Vec<Conflict>,
+}
+
+impl Layers {
+    /// Adds an input on top of the inputs that have already been added.
+    pub fn add(&mut self, name: &str, object: Object) {
+        let idx = self.names.len();
+        self.names.push(name.to_string());
+
+        let addrs: BTreeSet<Addr> = object.loadables.iter().map(|(a, _)| *a).collect();
+
+        // Which earlier inputs lose words to this one:
+        let mut replaced: BTreeMap<usize, Vec<Addr>> = BTreeMap::new();
+        for addr in addrs.iter() {
+            if let Some(owner) = self.owners.insert(*addr, idx) {
+                replaced.entry(owner).or_default().push(*addr);
+            }
+        }
+
+        for (owner, addrs) in replaced {
+            let replaced = &self.names[owner];
+
+            self.conflicts
+                .extend(ranges(addrs).into_iter().map(|range| Conflict {
+                    range,
+                    replaced: replaced.clone(),
+                    kept: name.to_string(),
+                }));
+        }
+
+        self.object
+            .loadables
+            .retain(|(addr, _)| !addrs.contains(addr));
+        self.object.loadables.extend(object.loadables);
+
+        self.object
+            .source_lines
+            .retain(|addr, _| !addrs.contains(addr));
+        self.object.source_lines.extend(object.source_lines);
+
+        self.object.symbols.merge(object.symbols);
+    }
+
+    /// Whether any input has a word at the given address.
+    pub fn is_used(&self, addr: Addr) -> bool {
+        self.owners.contains_key(&addr)
+    }
+
+    /// The places where inputs overlap, in the order they were found.
+    pub fn conflicts(&self) -> &[Conflict] {
+        &self.conflicts
+    }
+
+    /// Which input each range of addresses ended up coming from, sorted by
+    /// address.
+    pub fn ownership(&self) -> Vec<(RangeInclusive<Addr>, &str)> {
+        let mut ownership: Vec<(RangeInclusive<Addr>, &str)> = Vec::new();
+
+        for (addr, owner) in self.owners.iter() {
+            let name = self.names[*owner].as_str();
+
+            match ownership.last_mut() {
+                Some((r, n)) if *n == name && u32::from(*r.end()) + 1 == u32::from(*addr) => {
+                    *r = *r.start()..=*addr
+                }
+                _ => ownership.push((*addr..=*addr, name)),
+            }
+        }
+
+        ownership
+    }
+
+    /// Returns the program made up of all the inputs, sorted by address so
+    /// that words from later inputs end up in the segments around them.
+    pub fn into_object(mut self) -> Object {
+        self.object.loadables.sort_by_key(|(addr, _)| *addr);
+        self.object
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn object(orig: Addr, words: &[u16]) -> Object {
+        (orig..)
+            .zip(words.iter().copied())
+            .collect::<Vec<_>>()
+            .into()
+    }
+
+    #[test]
+    fn overlapping_inputs() {
+        let mut layers = Layers::default();
+        layers.add("a.obj", object(0x3000, &[1, 2, 3, 4, 5, 6]));
+        layers.add("b.obj", object(0x3001, &[7]));
+
+        assert_eq!(
+            layers.conflicts(),
+            [Conflict {
+                range: 0x3001..=0x3001,
+                replaced: "a.obj".to_string(),
+                kept: "b.obj".to_string(),
+            }]
+        );
+        assert_eq!(
+            layers.ownership(),
+            vec![
+                (0x3000..=0x3000, "a.obj"),
+                (0x3001..=0x3001, "b.obj"),
+                (0x3002..=0x3005, "a.obj"),
+            ]
+        );
+
+        // Still one segment:
+        assert_eq!(
+            layers.into_object().loadables,
+            object(0x3000, &[1, 7, 3, 4, 5, 6]).loadables
+        );
+    }
+
+    #[test]
+    fn separate_inputs() {
+        let mut layers = Layers::default();
+        layers.add("b.obj", object(0x4000, &[3]));
+        layers.add("a.obj", object(0x3000, &[1, 2]));
+
+        assert!(layers.conflicts().is_empty());
+        assert!(layers.is_used(0x4000) && !layers.is_used(0x3002));
+        assert_eq!(
+            layers.into_object().loadables,
+            vec![(0x3000, 1), (0x3001, 2), (0x4000, 3)]
+        );
+    }
+}
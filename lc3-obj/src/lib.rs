@@ -0,0 +1,101 @@
+//! Reading, writing, and converting LC-3 object files.
+//!
+//! - [`file_formats`] has parsers and writers for the formats we know about,
+//!   along with [format detection](file_formats::Detection).
+//! - [`input`] reads files in a given format into [programs](file_formats::Object).
+//! - [`image`] builds memory images (on top of an OS or not) and [`output`]
+//!   writes programs out in any of the formats.
+//! - [`analysis`], [`validation`], [`layering`], [`linker`], [`disasm`],
+//!   [`dot`], [`diff`], and [`inspect`] work on programs.
+//!
+//! Everything but the object file formats needs the (default) `std` feature.
+//! Without it the crate is `no_std` (but needs `alloc`) and the formats can
+//! only be detected, parsed, and written to and from bytes that are already
+//! in memory (i.e. on the UTP device, or in the browser).
+
+#![cfg_attr(not(feature = "std"), no_std)]
+// TODO: forbid
+#![forbid(
+    const_err,
+    dead_code,
+    improper_ctypes,
+    non_shorthand_field_patterns,
+    no_mangle_generic_items,
+    overflowing_literals,
+    path_statements,
+    patterns_in_fns_without_body,
+    plugin_as_library,
+    private_in_public,
+    unconditional_recursion,
+    unused_allocation,
+    unused_lifetimes,
+    unused_comparisons,
+    unused_parens,
+    while_true
+)]
+// TODO: deny
+#![deny(
+    // Not forbidden because serde's derives `allow` some of these.
+    bad_style,
+    unused,
+    missing_debug_implementations,
+    intra_doc_link_resolution_failure,
+    missing_docs,
+    unsafe_code,
+    trivial_casts,
+    trivial_numeric_casts,
+    unused_extern_crates,
+    unused_import_braces,
+    unused_qualifications,
+    unused_results,
+    rust_2018_idioms
+)]
+#![doc(test(attr(deny(warnings))))]
+#![doc(html_logo_url = "")] // TODO!
+
+extern crate alloc;
+
+use lc3_isa::{Addr, Word};
+
+/// A simple address word pair.
+pub type Loadable = (Addr, Word);
+
+pub mod file_formats;
+
+/// The examples in the README, so that they're tested.
+#[cfg(doctest)]
+#[doc = include_str!("../README.md")]
+pub struct ReadmeDoctests;
+
+#[cfg(feature = "std")]
+pub mod analysis;
+
+#[cfg(feature = "std")]
+pub mod validation;
+
+#[cfg(feature = "std")]
+pub mod layering;
+
+#[cfg(feature = "std")]
+pub mod linker;
+
+#[cfg(feature = "std")]
+pub mod disasm;
+
+#[cfg(feature = "std")]
+pub mod dot;
+
+#[cfg(feature = "std")]
+pub mod diff;
+
+#[cfg(feature = "std")]
+pub mod inspect;
+
+#[cfg(feature = "std")]
+pub mod input;
+
+#[cfg(feature = "std")]
+pub mod image;
+
+#[cfg(feature = "std")]
+pub mod output;
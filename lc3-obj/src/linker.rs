@@ -0,0 +1,662 @@
+//! Puts [relocatable object files](crate::file_formats::reloc) together into
+//! a program: places their sections, resolves the labels they refer to (across
+//! files), and fills in the words that refer to labels.
+use super::file_formats::reloc::{RelocKind, RelocatableObjFile, Section};
+use super::file_formats::{Object, SymbolTable};
+
+use lc3_isa::{Addr, Word};
+
+use std::collections::{BTreeMap, HashMap};
+use std::convert::TryFrom;
+use std::fmt::{self, Display};
+
+/// Ways linking can fail.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LinkError {
+    /// More than one file exports the same label.
+    DuplicateExport {
+        /// The label.
+        symbol: String,
+        /// The file that exported it first.
+        first: String,
+        /// The file that exported it again.
+        second: String,
+    },
+    /// A file defines the same label more than once.
+    DuplicateLabel {
+        /// The label.
+        symbol: String,
+        /// The file that defines it.
+        file: String,
+    },
+    /// A label or relocation is on a word past the end of its section.
+    PastTheEnd {
+        /// The section.
+        section: String,
+        /// The file the section is in.
+        file: String,
+        /// The offset (which word of the section) that isn't there.
+        offset: Addr,
+    },
+    /// A file imports a label that no file exports.
+    Undefined {
+        /// The label.
+        symbol: String,
+        /// The file that imports it.
+        file: String,
+    },
+    /// A file refers to a label that it doesn't define or import.
+    NotImported {
+        /// The label.
+        symbol: String,
+        /// The file that refers to it.
+        file: String,
+    },
+    /// A section doesn't fit anywhere in memory.
+    NoRoom {
+        /// The section.
+        section: String,
+        /// The file the section is in.
+        file: String,
+    },
+    /// Two pinned sections overlap.
+    Overlap {
+        /// One of the sections.
+        first: String,
+        /// The other section.
+        second: String,
+    },
+    /// A pinned section lands on words that another input already has.
+    Occupied {
+        /// The section.
+        section: String,
+        /// The file the section is in.
+        file: String,
+        /// The first address that's already in use.
+        addr: Addr,
+    },
+    /// A PC-relative reference is too far away from its label to fit in the
+    /// instruction's offset field.
+    OutOfRange {
+        /// Where the instruction is.
+        addr: Addr,
+        /// What the instruction refers to.
+        symbol: String,
+        /// The offset that didn't fit.
+        offset: i32,
+        /// The offset field.
+        kind: RelocKind,
+    },
+    /// A `FILL` refers to a label (plus its addend) that isn't an address.
+    NotAnAddress {
+        /// Where the word is.
+        addr: Addr,
+        /// What the word refers to.
+        symbol: String,
+        /// The label's address plus the addend.
+        target: i32,
+    },
+    /// A PC-relative relocation is on a word that isn't an instruction with
+    /// that offset field.
+    NoOffsetField {
+        /// Where the word is.
+        addr: Addr,
+        /// The word.
+        word: Word,
+        /// The offset field.
+        kind: RelocKind,
+    },
+}
+
+impl Display for LinkError {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use LinkError::*;
+
+        match self {
+            DuplicateExport {
+                symbol,
+                first,
+                second,
+            } => write!(
+                fmt,
+                "`{}` is exported by both `{}` and `{}`",
+                symbol, first, second
+            ),
+            DuplicateLabel { symbol, file } => {
+                write!(fmt, "`{}` defines `{}` more than once", file, symbol)
+            }
+            PastTheEnd {
+                section,
+                file,
+                offset,
+            } => write!(
+                fmt,
+                "section `{}` of `{}` has no word at offset {:X}",
+                section, file, offset
+            ),
+            Undefined { symbol, file } => write!(
+                fmt,
+                "`{}` imports `{}` but nothing exports it",
+                file, symbol
+            ),
+            NotImported { symbol, file } => write!(
+                fmt,
+                "`{}` refers to `{}` without defining or importing it",
+                file, symbol
+            ),
+            NoRoom { section, file } => write!(
+                fmt,
+                "there's no room in memory for section `{}` of `{}`",
+                section, file
+            ),
+            Overlap { first, second } => {
+                write!(fmt, "sections `{}` and `{}` overlap", first, second)
+            }
+            Occupied {
+                section,
+                file,
+                addr,
+            } => write!(
+                fmt,
+                "section `{}` of `{}` is pinned on top of x{:04X}, which another input already uses",
+                section, file, addr
+            ),
+            OutOfRange {
+                addr,
+                symbol,
+                offset,
+                kind,
+            } => write!(
+                fmt,
+                "x{:04X} refers to `{}`, which is {} words away; that doesn't fit in a {} offset",
+                addr, symbol, offset, kind
+            ),
+            NotAnAddress {
+                addr,
+                symbol,
+                target,
+            } => write!(
+                fmt,
+                "x{:04X} refers to `{}` at {}, which isn't an address",
+                addr, symbol, target
+            ),
+            NoOffsetField { addr, word, kind } => write!(
+                fmt,
+                "x{:04X} ({:04X}) isn't an instruction with a {} offset to fill in",
+                addr, word, kind
+            ),
+        }
+    }
+}
+
+/// Result type for linking.
+pub type LinkResult<T> = Result<T, LinkError>;
+
+/// The error for a label or relocation that's past the end of its section.
+fn past_the_end(file: &str, section: &Section, offset: Addr) -> LinkError {
+    LinkError::PastTheEnd {
+        section: section.name.clone(),
+        file: file.to_string(),
+        offset,
+    }
+}
+
+/// Decides where every section goes. Returns the origin of every section of
+/// every file.
+///
+/// Pinned sections go where they're pinned, as long as that isn't already used
+/// (by another section or by `is_used`). The rest are placed in order,
+/// starting at `base`, in the first gap that's big enough that isn't already
+/// used.
+fn place(
+    files: &[(String, RelocatableObjFile)],
+    base: Addr,
+    is_used: &dyn Fn(Addr) -> bool,
+) -> LinkResult<Vec<Vec<Addr>>> {
+    // (start, end (exclusive), section name) of everything placed so far:
+    let mut taken: Vec<(u32, u32, String)> = Vec::new();
+    let name = |file: &str, s: &Section| format!("{}:{}", file, s.name);
+
+    for (file, obj) in files.iter() {
+        for s in obj.sections.iter() {
+            if let Some(orig) = s.orig {
+                let (start, end) = (u32::from(orig), u32::from(orig) + s.words.len() as u32);
+                if end > 0x1_0000 {
+                    return Err(LinkError::NoRoom {
+                        section: s.name.clone(),
+                        file: file.clone(),
+                    });
+                }
+
+                if let Some((_, _, other)) = taken.iter().find(|(a, b, _)| start < *b && *a < end) {
+                    return Err(LinkError::Overlap {
+                        first: other.clone(),
+                        second: name(file, s),
+                    });
+                }
+
+                if let Some(used) = (start..end).find(|a| is_used(*a as Addr)) {
+                    return Err(LinkError::Occupied {
+                        section: s.name.clone(),
+                        file: file.clone(),
+                        addr: used as Addr,
+                    });
+                }
+
+                taken.push((start, end, name(file, s)));
+            }
+        }
+    }
+
+    let mut next = u32::from(base);
+    let mut origins = Vec::with_capacity(files.len());
+
+    for (file, obj) in files.iter() {
+        let mut file_origins = Vec::with_capacity(obj.sections.len());
+
+        for s in obj.sections.iter() {
+            if let Some(orig) = s.orig {
+                file_origins.push(orig);
+                continue;
+            }
+
+            let len = s.words.len() as u32;
+            let mut start = next;
+            loop {
+                let end = start + len;
+                if end > 0x1_0000 {
+                    return Err(LinkError::NoRoom {
+                        section: s.name.clone(),
+                        file: file.clone(),
+                    });
+                }
+
+                if let Some((_, b, _)) = taken.iter().find(|(a, b, _)| start < *b && *a < end) {
+                    start = *b;
+                } else if let Some(used) = (start..end).find(|a| is_used(*a as Addr)) {
+                    start = used + 1;
+                } else {
+                    break;
+                }
+            }
+
+            taken.push((start, start + len, name(file, s)));
+            file_origins.push(start as Addr);
+            next = start + len;
+        }
+
+        origins.push(file_origins);
+    }
+
+    Ok(origins)
+}
+
+/// Links relocatable object files together.
+///
+/// Sections that aren't pinned to an address are placed starting at `base`,
+/// skipping over addresses for which `is_used` returns true (i.e. addresses
+/// that absolute inputs already have words at).
+///
+/// Returns a program for each file (with the labels it defines) in the same
+/// order as the files.
+///
+/// Files don't have to come from [parsing](crate::file_formats::Relocatable);
+/// labels and relocations that are past the end of their section are errors.
+pub fn link(
+    files: &[(String, RelocatableObjFile)],
+    base: Addr,
+    is_used: &dyn Fn(Addr) -> bool,
+) -> LinkResult<Vec<(String, Object)>> {
+    let origins = place(files, base, is_used)?;
+
+    // Labels, by file (for local labels) and across files (for exported ones):
+    let mut exports: HashMap<&str, (Addr, &str)> = HashMap::new();
+    let mut locals: Vec<HashMap<&str, Addr>> = Vec::with_capacity(files.len());
+
+    for ((file, obj), origins) in files.iter().zip(origins.iter()) {
+        let mut file_locals = HashMap::new();
+
+        for (s, orig) in obj.sections.iter().zip(origins.iter()) {
+            for sym in s.symbols.iter() {
+                if usize::from(sym.offset) >= s.words.len() {
+                    return Err(past_the_end(file, s, sym.offset));
+                }
+
+                let addr = orig.wrapping_add(sym.offset);
+                if file_locals.insert(sym.name.as_str(), addr).is_some() {
+                    return Err(LinkError::DuplicateLabel {
+                        symbol: sym.name.clone(),
+                        file: file.clone(),
+                    });
+                }
+
+                if sym.exported {
+                    if let Some((_, first)) = exports.insert(&sym.name, (addr, file)) {
+                        return Err(LinkError::DuplicateExport {
+                            symbol: sym.name.clone(),
+                            first: first.to_string(),
+                            second: file.clone(),
+                        });
+                    }
+                }
+            }
+        }
+
+        locals.push(file_locals);
+    }
+
+    for (file, obj) in files.iter() {
+        if let Some(import) = obj
+            .imports
+            .iter()
+            .find(|i| !exports.contains_key(i.as_str()))
+        {
+            return Err(LinkError::Undefined {
+                symbol: import.clone(),
+                file: file.clone(),
+            });
+        }
+    }
+
+    let mut linked = Vec::with_capacity(files.len());
+
+    for (((file, obj), origins), locals) in files.iter().zip(origins).zip(locals.iter()) {
+        let lookup = |symbol: &str| match locals.get(symbol) {
+            Some(addr) => Ok(*addr),
+            // Imports have all been checked to exist:
+            None if obj.imports.iter().any(|i| i == symbol) => Ok(exports[symbol].0),
+            None => Err(LinkError::NotImported {
+                symbol: symbol.to_string(),
+                file: file.clone(),
+            }),
+        };
+
+        let mut loadables = Vec::new();
+        let mut symbols = SymbolTable::default();
+
+        for (s, orig) in obj.sections.iter().zip(origins) {
+            let mut words: BTreeMap<Addr, Word> = s
+                .words
+                .iter()
+                .enumerate()
+                .map(|(idx, word)| (orig.wrapping_add(idx as Addr), *word))
+                .collect();
+
+            for r in s.relocations.iter() {
+                let addr = orig.wrapping_add(r.offset);
+                let target = i32::from(lookup(&r.symbol)?) + r.addend;
+                let word = words
+                    .get_mut(&addr)
+                    .filter(|_| usize::from(r.offset) < s.words.len())
+                    .ok_or_else(|| past_the_end(file, s, r.offset))?;
+
+                if !r.kind.applies_to(*word) {
+                    return Err(LinkError::NoOffsetField {
+                        addr,
+                        word: *word,
+                        kind: r.kind,
+                    });
+                }
+
+                let (bits, mask) = match r.kind {
+                    RelocKind::Fill => {
+                        *word = Word::try_from(target).map_err(|_| LinkError::NotAnAddress {
+                            addr,
+                            symbol: r.symbol.clone(),
+                            target,
+                        })?;
+                        continue;
+                    }
+                    RelocKind::PcOffset9 => (9, 0x1FF),
+                    RelocKind::PcOffset11 => (11, 0x7FF),
+                };
+
+                // The PC has already been incremented when the offset is applied:
+                let offset = target - (i32::from(addr) + 1);
+                if offset < -(1 << (bits - 1)) || offset >= (1 << (bits - 1)) {
+                    return Err(LinkError::OutOfRange {
+                        addr,
+                        symbol: r.symbol.clone(),
+                        offset,
+                        kind: r.kind,
+                    });
+                }
+
+                *word = (*word & !mask) | (offset as Word & mask);
+            }
+
+            loadables.extend((0..s.words.len()).map(|idx| {
+                let addr = orig.wrapping_add(idx as Addr);
+                (addr, words[&addr])
+            }));
+
+            s.symbols
+                .iter()
+                .for_each(|sym| symbols.insert(&sym.name, orig.wrapping_add(sym.offset)));
+        }
+
+        linked.push((
+            file.clone(),
+            Object {
+                loadables,
+                symbols,
+                ..Object::default()
+            },
+        ));
+    }
+
+    Ok(linked)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_formats::reloc::{Relocatable, Relocation, Symbol};
+
+    fn file(name: &str, text: &str) -> (String, RelocatableObjFile) {
+        let obj = Relocatable::parse_bytes(text.as_bytes()).unwrap();
+        (name.to_string(), obj)
+    }
+
+    fn nothing_used(_: Addr) -> bool {
+        false
+    }
+
+    fn words(linked: &[(String, Object)], idx: usize) -> Vec<(Addr, Word)> {
+        linked[idx].1.loadables.clone()
+    }
+
+    const PRINT: &str = "
+        .LC3REL
+        .SECTION print
+        .ORIG 3200
+        .WORDS F022 C1C0
+        .EXPORT PRINT 0
+    ";
+
+    #[test]
+    fn symbols_are_resolved_across_files() {
+        let main = "
+            .LC3REL
+            .IMPORT PRINT
+            .SECTION main
+            .WORDS E000 4800 F025 0000 0 0 0 0 0 0 0 0 0 0 0 0 0 0
+            .LOCAL MSG 11
+            .RELOC 0 PC9 MSG
+            .RELOC 1 PC11 PRINT
+            .RELOC 3 FILL MSG -1
+        ";
+        let files = [file("main.rel", main), file("print.rel", PRINT)];
+        let linked = link(&files, 0x3000, &nothing_used).unwrap();
+
+        let main = words(&linked, 0);
+        // LEA R0, MSG (x3011 is 16 words after x3001):
+        assert_eq!(main[0], (0x3000, 0xE010));
+        // JSR PRINT (x3200 is 0x1FE words after x3002):
+        assert_eq!(main[1], (0x3001, 0x49FE));
+        assert_eq!(main[3], (0x3003, 0x3010));
+
+        assert_eq!(linked[0].1.symbols.label_for(0x3011), Some("MSG"));
+        assert_eq!(words(&linked, 1), vec![(0x3200, 0xF022), (0x3201, 0xC1C0)]);
+    }
+
+    #[test]
+    fn unpinned_sections_skip_used_words() {
+        let lib = ".LC3REL\n.SECTION lib\n.WORDS 1 2\n";
+        let files = [file("lib.rel", lib)];
+        let linked = link(&files, 0x3000, &|addr| addr == 0x3001).unwrap();
+
+        assert_eq!(words(&linked, 0), vec![(0x3002, 1), (0x3003, 2)]);
+    }
+
+    #[test]
+    fn pinned_sections_cant_land_on_used_words() {
+        let files = [file("print.rel", PRINT)];
+
+        assert_eq!(
+            link(&files, 0x3000, &|addr| addr == 0x3201),
+            Err(LinkError::Occupied {
+                section: "print".to_string(),
+                file: "print.rel".to_string(),
+                addr: 0x3201,
+            })
+        );
+    }
+
+    #[test]
+    fn offsets_out_of_range() {
+        let far = "
+            .LC3REL
+            .IMPORT PRINT
+            .SECTION main
+            .ORIG 3000
+            .WORDS 0E00
+            .RELOC 0 PC9 PRINT
+        ";
+        let files = [file("far.rel", far), file("print.rel", PRINT)];
+
+        assert_eq!(
+            link(&files, 0x3000, &nothing_used),
+            Err(LinkError::OutOfRange {
+                addr: 0x3000,
+                symbol: "PRINT".to_string(),
+                offset: 0x1FF,
+                kind: RelocKind::PcOffset9,
+            })
+        );
+    }
+
+    #[test]
+    fn fills_have_to_be_addresses() {
+        let fill = ".LC3REL\n.SECTION s\n.ORIG FFFF\n.WORDS 0\n.LOCAL END 0\n.RELOC 0 FILL END 1\n";
+        let files = [file("fill.rel", fill)];
+
+        assert_eq!(
+            link(&files, 0x3000, &nothing_used),
+            Err(LinkError::NotAnAddress {
+                addr: 0xFFFF,
+                symbol: "END".to_string(),
+                target: 0x1_0000,
+            })
+        );
+    }
+
+    #[test]
+    fn relocations_need_the_right_instruction() {
+        // ADD R0, R0, #1 has no PCoffset9:
+        let add = ".LC3REL\n.SECTION s\n.WORDS 1021\n.LOCAL HERE 0\n.RELOC 0 PC9 HERE\n";
+        // JSRR R1 has no PCoffset11:
+        let jsrr = ".LC3REL\n.SECTION s\n.WORDS 4040\n.LOCAL HERE 0\n.RELOC 0 PC11 HERE\n";
+
+        assert_eq!(
+            link(&[file("add.rel", add)], 0x3000, &nothing_used),
+            Err(LinkError::NoOffsetField {
+                addr: 0x3000,
+                word: 0x1021,
+                kind: RelocKind::PcOffset9,
+            })
+        );
+        assert!(matches!(
+            link(&[file("jsrr.rel", jsrr)], 0x3000, &nothing_used),
+            Err(LinkError::NoOffsetField { word: 0x4040, .. })
+        ));
+    }
+
+    #[test]
+    fn duplicate_labels() {
+        let files = [file("a.rel", PRINT), file("b.rel", PRINT)];
+        assert!(matches!(
+            link(&files, 0x3000, &nothing_used),
+            Err(LinkError::Overlap { .. })
+        ));
+
+        let other = PRINT.replace(".ORIG 3200", ".ORIG 5000");
+        let files = [file("a.rel", PRINT), file("b.rel", &other)];
+        assert_eq!(
+            link(&files, 0x3000, &nothing_used),
+            Err(LinkError::DuplicateExport {
+                symbol: "PRINT".to_string(),
+                first: "a.rel".to_string(),
+                second: "b.rel".to_string(),
+            })
+        );
+
+        let twice = format!("{}\n.LOCAL PRINT 1\n", PRINT);
+        assert_eq!(
+            link(&[file("twice.rel", &twice)], 0x3000, &nothing_used),
+            Err(LinkError::DuplicateLabel {
+                symbol: "PRINT".to_string(),
+                file: "twice.rel".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn offsets_past_the_end() {
+        let (name, obj) = file(
+            "bad.rel",
+            ".LC3REL\n.SECTION s\n.WORDS 0E00\n.LOCAL HERE 0\n",
+        );
+        let past_the_end = Err(LinkError::PastTheEnd {
+            section: "s".to_string(),
+            file: "bad.rel".to_string(),
+            offset: 1,
+        });
+
+        let mut reloc = obj.clone();
+        reloc.sections[0].relocations.push(Relocation {
+            offset: 1,
+            kind: RelocKind::PcOffset9,
+            symbol: "HERE".to_string(),
+            addend: 0,
+        });
+        assert_eq!(
+            link(&[(name.clone(), reloc)], 0x3000, &nothing_used),
+            past_the_end
+        );
+
+        let mut label = obj;
+        label.sections[0].symbols.push(Symbol {
+            name: "THERE".to_string(),
+            offset: 1,
+            exported: false,
+        });
+        assert_eq!(link(&[(name, label)], 0x3000, &nothing_used), past_the_end);
+    }
+
+    #[test]
+    fn undefined_labels() {
+        let main = ".LC3REL\n.IMPORT GONE\n.SECTION s\n.WORDS 0E00\n.RELOC 0 PC9 GONE\n";
+        assert!(matches!(
+            link(&[file("main.rel", main)], 0x3000, &nothing_used),
+            Err(LinkError::Undefined { .. })
+        ));
+
+        let main = ".LC3REL\n.SECTION s\n.WORDS 0E00\n.RELOC 0 PC9 PRINT\n";
+        let files = [file("main.rel", main), file("print.rel", PRINT)];
+        assert!(matches!(
+            link(&files, 0x3000, &nothing_used),
+            Err(LinkError::NotImported { .. })
+        ));
+    }
+}
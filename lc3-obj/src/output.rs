@@ -0,0 +1,169 @@
+//! Writing programs out in any of the [formats](Format) that can be written.
+use super::analysis::Analysis;
+use super::disasm;
+use super::dot;
+use super::file_formats::lc3tools::Lc3ToolsObjFile;
+use super::file_formats::structured::StructuredObjFile;
+use super::file_formats::{
+    CArray, Coe, Format, ImageWriter, IntelHex, Json, Layout, Lc3AsBin, Lc3AsHex, Lc3Tools,
+    Lumetta, MemDump, Mif, ObjFileWriter, Object, ReadMemH, RustStatic, SRecord, SingleSegment,
+    Yaml,
+};
+use super::image::{build_image, OsStrategy};
+use super::Loadable;
+
+use lc3_isa::Addr;
+
+use std::ffi::OsString;
+use std::fs::File;
+use std::io::{Error, ErrorKind, Result as IoResult, Write};
+use std::path::{Path, PathBuf};
+
+/// Writes the program out to `path` in the given format. Returns the files
+/// that were written.
+///
+/// Formats that can only hold one segment get a file per segment when there's
+/// more than one (see [`segment_path`](segment_path)); everything else is
+/// like [`write_to`](write_to).
+pub fn write<P: AsRef<Path>>(
+    path: P,
+    format: Format,
+    object: Object,
+    os: OsStrategy<'_>,
+    layout: Layout,
+    analysis: &Analysis,
+) -> IoResult<Vec<PathBuf>> {
+    let path = path.as_ref();
+
+    match format {
+        Format::Relocatable => Err(not_writable(format)),
+        Format::Lumetta => write_segments::<&Lumetta>(path, object.into()),
+        Format::Lc3AsHex => write_segments::<Lc3AsHex>(path, object.into()),
+        Format::Lc3AsBin => write_segments::<Lc3AsBin>(path, object.into()),
+        _ => {
+            let file = &mut File::create(path)?;
+            write_to(file, format, object, os, layout, analysis)?;
+
+            Ok(vec![path.to_path_buf()])
+        }
+    }
+}
+
+/// Writes the program out in the given format.
+///
+/// Memory images start out as whatever `os` says; `layout` is only used for
+/// formats that are source code. The analysis is used to tell code apart from
+/// data.
+///
+/// Fails for programs with more than one segment in formats that can only
+/// hold one segment.
+pub fn write_to<W: Write>(
+    file: &mut W,
+    format: Format,
+    object: Object,
+    os: OsStrategy<'_>,
+    layout: Layout,
+    analysis: &Analysis,
+) -> IoResult<()> {
+    match format {
+        Format::Lc3Tools => <Lc3Tools<'_>>::write(
+            &Lc3ToolsObjFile::with_source_lines(object.loadables, &object.source_lines),
+            file,
+        ),
+        Format::Lumetta => write_single::<&Lumetta, _>(file, object.into()),
+        Format::IntelHex => IntelHex::write(&object.loadables.into(), file),
+        Format::SRecord => SRecord::write(&object.loadables.into(), file),
+        Format::Lc3AsHex => write_single::<Lc3AsHex, _>(file, object.into()),
+        Format::Lc3AsBin => write_single::<Lc3AsBin, _>(file, object.into()),
+        Format::Json => Json::write(&StructuredObjFile::new(&object, analysis), file),
+        Format::Yaml => Yaml::write(&StructuredObjFile::new(&object, analysis), file),
+        Format::Asm => disasm::write(&object, analysis, file),
+        Format::Dot => dot::write(&object, analysis, file),
+        Format::Relocatable => Err(not_writable(format)),
+        Format::MemoryDump
+        | Format::ReadMemH
+        | Format::Coe
+        | Format::Mif
+        | Format::CArray
+        | Format::RustStatic => {
+            let image = build_image(object.loadables, os)?;
+
+            match format {
+                Format::ReadMemH => ReadMemH.write(&image, file),
+                Format::Coe => Coe.write(&image, file),
+                Format::Mif => Mif.write(&image, file),
+                Format::CArray => CArray { layout }.write(&image, file),
+                Format::RustStatic => RustStatic { layout }.write(&image, file),
+                _ => MemDump.write(&image, file),
+            }
+        }
+    }
+}
+
+/// The error for formats that can't be written.
+fn not_writable(format: Format) -> Error {
+    Error::new(
+        ErrorKind::InvalidInput,
+        format!(
+            "Writing {} isn't supported; they can only be linked.",
+            format.description()
+        ),
+    )
+}
+
+/// For formats that can only hold one segment, when everything has to go in
+/// one file.
+fn write_single<F: ObjFileWriter, W: Write>(file: &mut W, program: Vec<Loadable>) -> IoResult<()>
+where
+    F::Return: SingleSegment,
+{
+    match F::from_loadables(program).split().as_slice() {
+        [obj] => F::write(obj, file),
+        objs => Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!(
+                "{} can only hold one segment and the program has {}; write it to a file instead.",
+                F::NAME,
+                objs.len()
+            ),
+        )),
+    }
+}
+
+/// For formats that can only hold one segment; when there are multiple, each
+/// one gets written to its own file (see [`segment_path`](segment_path)).
+fn write_segments<F: ObjFileWriter>(path: &Path, program: Vec<Loadable>) -> IoResult<Vec<PathBuf>>
+where
+    F::Return: SingleSegment,
+{
+    let objs = F::from_loadables(program).split();
+
+    if let [obj] = objs.as_slice() {
+        F::write(obj, &mut File::create(path)?)?;
+        return Ok(vec![path.to_path_buf()]);
+    }
+
+    objs.iter()
+        .map(|obj| {
+            let path = segment_path(path, obj.orig().unwrap_or_default());
+            F::write(obj, &mut File::create(&path)?)?;
+
+            Ok(path)
+        })
+        .collect()
+}
+
+/// Where a segment goes when a program's segments are written to separate
+/// files: the segment's origin is added to the file name (i.e. `out.obj`
+/// becomes `out.x3000.obj`, `out.x4000.obj`).
+pub fn segment_path(path: &Path, orig: Addr) -> PathBuf {
+    let mut name = path.file_stem().map(OsString::from).unwrap_or_default();
+    name.push(format!(".x{:04X}", orig));
+
+    if let Some(ext) = path.extension() {
+        name.push(".");
+        name.push(ext);
+    }
+
+    path.with_file_name(name)
+}
@@ -0,0 +1,158 @@
+//! Checks for programs that can't be loaded the way they're laid out: words
+//! that wrap around the end of memory, words that are placed more than once,
+//! and words that land on top of the OS or the memory mapped device registers.
+use super::Loadable;
+
+use lc3_isa::Addr;
+
+use std::collections::BTreeMap;
+use std::fmt::{self, Display};
+use std::ops::RangeInclusive;
+
+/// The OS and the TRAP/interrupt vector tables live here.
+pub const SYSTEM_SPACE: RangeInclusive<Addr> = 0x0000..=0x2FFF;
+
+/// User programs go here.
+pub const USER_SPACE: RangeInclusive<Addr> = 0x3000..=0xFDFF;
+
+/// The memory mapped device registers live here.
+pub const DEVICE_REGISTERS: RangeInclusive<Addr> = 0xFE00..=0xFFFF;
+
+/// Something wrong with where a program's words go.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Problem {
+    /// There are words at both the end of memory (0xFFFF) and the start of
+    /// memory (0x0000); the program wraps around.
+    Wraparound,
+    /// Words are placed at these addresses more than once.
+    Overlap(RangeInclusive<Addr>),
+    /// Words are placed in the part of memory that holds the OS.
+    SystemSpace(RangeInclusive<Addr>),
+    /// Words are placed on the memory mapped device registers.
+    DeviceRegisters(RangeInclusive<Addr>),
+}
+
+impl Display for Problem {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Problem::Wraparound => write!(fmt, "a segment wraps around from xFFFF to x0000"),
+            Problem::Overlap(r) => write!(fmt, "{} is written more than once", range(r)),
+            Problem::SystemSpace(r) => write!(fmt, "{} overwrites OS/system space", range(r)),
+            Problem::DeviceRegisters(r) => {
+                write!(fmt, "{} overwrites the device register page", range(r))
+            }
+        }
+    }
+}
+
+/// Formats a range of addresses the way the assembler would write them, i.e.
+/// `x3000-x30FF` (or just `x3000` for a single address).
+pub fn range(r: &RangeInclusive<Addr>) -> String {
+    if r.start() == r.end() {
+        format!("x{:04X}", r.start())
+    } else {
+        format!("x{:04X}-x{:04X}", r.start(), r.end())
+    }
+}
+
+/// Groups addresses (which must be sorted and unique) into ranges of
+/// contiguous addresses.
+pub fn ranges<I: IntoIterator<Item = Addr>>(addrs: I) -> Vec<RangeInclusive<Addr>> {
+    let mut ranges: Vec<RangeInclusive<Addr>> = Vec::new();
+
+    for addr in addrs {
+        match ranges.last_mut() {
+            Some(r) if u32::from(*r.end()) + 1 == u32::from(addr) => *r = *r.start()..=addr,
+            _ => ranges.push(addr..=addr),
+        }
+    }
+
+    ranges
+}
+
+/// Returns every problem with the given program.
+///
+/// Whether a problem matters depends on what the program is being turned into;
+/// i.e. words in system space are only a problem when the program is being
+/// layered onto an OS.
+pub fn check(loadables: &[Loadable]) -> Vec<Problem> {
+    let mut problems = Vec::new();
+
+    let mut counts: BTreeMap<Addr, usize> = BTreeMap::new();
+    loadables
+        .iter()
+        .for_each(|(addr, _)| *counts.entry(*addr).or_default() += 1);
+
+    // Segments are runs of contiguous addresses, so one that runs off the end
+    // of memory (in whatever order its words are in) has words at both ends:
+    if counts.contains_key(&Addr::MAX) && counts.contains_key(&0x0000) {
+        problems.push(Problem::Wraparound);
+    }
+
+    problems.extend(
+        ranges(counts.iter().filter(|(_, c)| **c > 1).map(|(a, _)| *a))
+            .into_iter()
+            .map(Problem::Overlap),
+    );
+
+    let within = |region: RangeInclusive<Addr>| {
+        ranges(counts.keys().copied().filter(move |a| region.contains(a)))
+    };
+
+    problems.extend(within(SYSTEM_SPACE).into_iter().map(Problem::SystemSpace));
+    problems.extend(
+        within(DEVICE_REGISTERS)
+            .into_iter()
+            .map(Problem::DeviceRegisters),
+    );
+
+    problems
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wraparound_does_not_depend_on_order() {
+        let mut program = vec![(0xFFFE, 1), (0xFFFF, 2), (0x0000, 3)];
+        assert_eq!(check(&program)[0], Problem::Wraparound);
+
+        program.sort();
+        assert_eq!(check(&program)[0], Problem::Wraparound);
+
+        program.reverse();
+        assert_eq!(check(&program)[0], Problem::Wraparound);
+    }
+
+    #[test]
+    fn problems() {
+        let program = [
+            (0x3000, 1),
+            (0x3001, 2),
+            (0x3001, 3),
+            (0x2FFF, 4),
+            (0xFE00, 5),
+        ];
+
+        assert_eq!(
+            check(&program),
+            vec![
+                Problem::Overlap(0x3001..=0x3001),
+                Problem::SystemSpace(0x2FFF..=0x2FFF),
+                Problem::DeviceRegisters(0xFE00..=0xFE00),
+            ]
+        );
+        assert_eq!(check(&[(0x3000, 1), (0xFDFF, 2)]), vec![]);
+    }
+
+    #[test]
+    fn ranges_are_contiguous_runs() {
+        assert_eq!(
+            ranges(vec![0x3000, 0x3001, 0x3003, 0xFFFF]),
+            vec![0x3000..=0x3001, 0x3003..=0x3003, 0xFFFF..=0xFFFF]
+        );
+        assert_eq!(range(&(0x3000..=0x30FF)), "x3000-x30FF");
+        assert_eq!(range(&(0x3000..=0x3000)), "x3000");
+    }
+}
@@ -2,9 +2,7 @@
 
 // TODO: forbid
 #![forbid(
-    bad_style,
     const_err,
-    dead_code,
     improper_ctypes,
     non_shorthand_field_patterns,
     no_mangle_generic_items,
@@ -22,6 +20,10 @@
 )]
 // TODO: deny
 #![deny(
+    // Not forbidden because serde's derives `allow` some of these.
+    bad_style,
+    // Not forbidden because the test harness `allow`s it on `main`.
+    dead_code,
     unused,
     missing_debug_implementations,
     intra_doc_link_resolution_failure,
@@ -38,23 +40,33 @@
 #![doc(test(attr(deny(warnings))))]
 #![doc(html_logo_url = "")] // TODO!
 
-use lc3_isa::util::MemoryDump;
-use lc3_isa::{Addr, Instruction, Word};
-use lc3_os::OS_IMAGE;
-use lc3_shims::memory::FileBackedMemoryShim;
+use lc3_isa::{Addr, Instruction};
 
+use std::collections::BTreeMap;
 use std::convert::TryFrom;
-use std::fs::File;
-use std::io::Result as IoResult;
-use std::path::Path;
+use std::fs::{self, File};
+use std::io::{self, Error, ErrorKind, Read, Result as IoResult, Write};
+use std::mem;
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+use std::process;
 
-use clap::{App, AppSettings, Arg};
+use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
 
-/// A simple address word pair.
-pub type Loadable = (Addr, Word);
+use lc3_obj::analysis::{pc_relative_target, Analysis};
+use lc3_obj::diff::{self, ChangeKind};
+use lc3_obj::file_formats::{
+    debug_info, Detection, Format, Layout, Lc3Tools, Object, SourceLine, SymbolTable,
+};
+use lc3_obj::image::OsStrategy;
+use lc3_obj::input::{read_as, Input, ReadOptions};
+use lc3_obj::inspect::Info;
+use lc3_obj::layering::Layers;
+use lc3_obj::validation::{self, range, Problem, USER_SPACE};
+use lc3_obj::{linker, output, Loadable};
 
-pub mod file_formats;
-use file_formats::{Lc3Tools, Lumetta, ObjFileFormat};
+/// The path that stands for stdin (for inputs) or stdout (for the output).
+const STDIO: &str = "-";
 
 macro_rules! cargo_env {
     ($cargo_env_var:ident) => {
@@ -65,26 +77,170 @@ macro_rules! cargo_env {
 fn args() -> App<'static, 'static> {
     App::new(cargo_env!(PKG_NAME))
         .setting(AppSettings::ArgRequiredElseHelp)
+        .setting(AppSettings::SubcommandsNegateReqs)
         .version(cargo_env!(PKG_VERSION))
         .author(cargo_env!(PKG_AUTHORS))
         .about(cargo_env!(PKG_DESCRIPTION))
         .arg(
             Arg::with_name("input")
                 .short("i")
-                .help("Input object file to convert.")
+                .help(
+                    "Input object file to convert. Can be given more than once; inputs are \
+                     layered on top of each other in order, with later inputs winning where \
+                     they overlap. `-` reads from stdin.",
+                )
                 .long("input")
                 .value_names(&["FILE"])
                 .number_of_values(1)
+                .multiple(true)
                 .required(true),
         )
         .arg(
             Arg::with_name("output")
                 .short("o")
-                .help("Output file.")
+                .help("Output file. `-` writes to stdout (and needs `--to`).")
                 .long("output")
                 .value_names(&["FILE"])
                 .number_of_values(1)
-                .required(true),
+                .required_unless("detect"),
+        )
+        .arg(
+            Arg::with_name("detect")
+                .short("d")
+                .help(
+                    "List the formats the input could be in and how likely each one is \
+                     without converting anything.",
+                )
+                .long("detect"),
+        )
+        .arg(
+            Arg::with_name("from")
+                .short("f")
+                .help(
+                    "Input format; applies to every input. Inferred from each input file if \
+                     not specified.",
+                )
+                .long("from")
+                .value_names(&["FORMAT"])
+                .possible_values(&[
+                    "lc3tools",
+                    "lumetta",
+                    "memdump",
+                    "ihex",
+                    "srec",
+                    "lc3as-hex",
+                    "lc3as-bin",
+                    "reloc",
+                    "json",
+                    "yaml",
+                ]),
+        )
+        .arg(
+            Arg::with_name("to")
+                .short("t")
+                .help(
+                    "Output format. Inferred from the output file's extension if not \
+                     specified. The OS options only apply to memory images.",
+                )
+                .long("to")
+                .value_names(&["FORMAT"])
+                .possible_values(&[
+                    "lc3tools",
+                    "lumetta",
+                    "memdump",
+                    "ihex",
+                    "srec",
+                    "lc3as-hex",
+                    "lc3as-bin",
+                    "readmemh",
+                    "coe",
+                    "mif",
+                    "c",
+                    "rust",
+                    "json",
+                    "yaml",
+                    "asm",
+                    "dot",
+                ]),
+        )
+        .arg(
+            Arg::with_name("layout")
+                .help(
+                    "How C and Rust outputs lay out the memory image: every word, only the \
+                     non-zero segments, or run-length encoded.",
+                )
+                .long("layout")
+                .value_names(&["LAYOUT"])
+                .possible_values(&["full", "sparse", "rle"])
+                .default_value("full"),
+        )
+        .arg(
+            Arg::with_name("region")
+                .short("r")
+                .help(
+                    "Only convert the words in this (inclusive) range of addresses, i.e. \
+                     `3000-FDFF`. Defaults to user space for memory dump inputs.",
+                )
+                .long("region")
+                .value_names(&["START-END"])
+                .number_of_values(1),
+        )
+        .arg(
+            Arg::with_name("base")
+                .help(
+                    "Where to start placing the sections of relocatable inputs that aren't \
+                     pinned to an address. Defaults to x3000.",
+                )
+                .long("base")
+                .value_names(&["ADDR"])
+                .number_of_values(1),
+        )
+        .arg(
+            Arg::with_name("entry")
+                .short("e")
+                .help(
+                    "Somewhere execution can start, besides the start of each segment and the \
+                     routines in the TRAP/interrupt vector tables. Used to tell code apart from \
+                     data when printing and disassembling.",
+                )
+                .long("entry")
+                .value_names(&["ADDR"])
+                .multiple(true)
+                .number_of_values(1),
+        )
+        .arg(
+            Arg::with_name("allow-lossy")
+                .short("l")
+                .help("Convert even if the output format can't hold everything in the input.")
+                .long("allow-lossy"),
+        )
+        .arg(
+            Arg::with_name("lenient")
+                .help(
+                    "Keep whatever can be parsed from a truncated input (i.e. a half written \
+                     LC3Tools object file) instead of failing.",
+                )
+                .long("lenient"),
+        )
+        .arg(
+            Arg::with_name("symbols")
+                .short("s")
+                .help(
+                    "Symbol table (`.sym` file) to go with the input. Used when printing and \
+                     kept in outputs that have labels (JSON, YAML, and assembly); other outputs \
+                     get a copy of it next to them.",
+                )
+                .long("symbols")
+                .value_names(&["FILE"])
+                .number_of_values(1),
+        )
+        .arg(
+            Arg::with_name("allow-os-overwrite")
+                .help(
+                    "Allow words in OS/system space (x0000-x2FFF) and on the device register \
+                     page (xFE00-xFFFF) when writing a memory image.",
+                )
+                .long("allow-os-overwrite"),
         )
         .arg(
             Arg::with_name("without-os")
@@ -106,14 +262,177 @@ fn args() -> App<'static, 'static> {
                 .help("Print out parsed object file.")
                 .long("verbose"),
         )
+        .subcommand(
+            SubCommand::with_name("inspect")
+                .about(
+                    "Summarizes an input: its format, segments, entry point, instruction mix, \
+                     the TRAPs it uses, and the device registers it touches.",
+                )
+                .arg(
+                    Arg::with_name("input")
+                        .help("The input to look at; `-` reads from stdin.")
+                        .value_name("INPUT")
+                        .required(true)
+                        .index(1),
+                )
+                .arg(
+                    Arg::with_name("from")
+                        .short("f")
+                        .help("Input format. Inferred from the input file if not specified.")
+                        .long("from")
+                        .value_names(&["FORMAT"])
+                        .possible_values(&[
+                            "lc3tools",
+                            "lumetta",
+                            "memdump",
+                            "ihex",
+                            "srec",
+                            "lc3as-hex",
+                            "lc3as-bin",
+                            "json",
+                            "yaml",
+                        ]),
+                )
+                .arg(
+                    Arg::with_name("region")
+                        .short("r")
+                        .help(
+                            "Only look at the words in this (inclusive) range of addresses, \
+                             i.e. `3000-FDFF`. Defaults to user space for memory dump inputs.",
+                        )
+                        .long("region")
+                        .value_names(&["START-END"])
+                        .number_of_values(1),
+                )
+                .arg(
+                    Arg::with_name("entry")
+                        .short("e")
+                        .help(
+                            "Somewhere execution can start, besides the start of each segment \
+                             and the routines in the TRAP/interrupt vector tables.",
+                        )
+                        .long("entry")
+                        .value_names(&["ADDR"])
+                        .multiple(true)
+                        .number_of_values(1),
+                )
+                .arg(
+                    Arg::with_name("json")
+                        .help("Print the summary as JSON instead of as a table.")
+                        .long("json"),
+                )
+                .arg(
+                    Arg::with_name("lenient")
+                        .help("Keep whatever can be parsed from a truncated input.")
+                        .long("lenient"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("diff")
+                .about(
+                    "Compares two inputs word by word. Exits with 0 if they're the same, 1 if \
+                     they differ, and 2 if something went wrong.",
+                )
+                .arg(
+                    Arg::with_name("old")
+                        .help("The input to compare against, i.e. the reference solution.")
+                        .value_name("OLD")
+                        .required(true)
+                        .index(1),
+                )
+                .arg(
+                    Arg::with_name("new")
+                        .help("The input to compare, i.e. a submission.")
+                        .value_name("NEW")
+                        .required(true)
+                        .index(2),
+                )
+                .arg(
+                    Arg::with_name("from")
+                        .short("f")
+                        .help(
+                            "Format of both inputs. Inferred from each input file if not \
+                             specified.",
+                        )
+                        .long("from")
+                        .value_names(&["FORMAT"])
+                        .possible_values(&[
+                            "lc3tools",
+                            "lumetta",
+                            "memdump",
+                            "ihex",
+                            "srec",
+                            "lc3as-hex",
+                            "lc3as-bin",
+                            "json",
+                            "yaml",
+                        ]),
+                )
+                .arg(
+                    Arg::with_name("region")
+                        .short("r")
+                        .help(
+                            "Only compare the words in this (inclusive) range of addresses, \
+                             i.e. `3000-FDFF`. Defaults to user space for memory dump inputs.",
+                        )
+                        .long("region")
+                        .value_names(&["START-END"])
+                        .number_of_values(1),
+                )
+                .arg(
+                    Arg::with_name("lenient")
+                        .help("Keep whatever can be parsed from truncated inputs.")
+                        .long("lenient"),
+                ),
+        )
 }
 
-fn print_loadable(loadable: impl Iterator<Item = (Addr, Word)>) {
-    loadable.for_each(|(a, w)| {
-        if let Ok(insn) = Instruction::try_from(w) {
-            println!("{:04X}: {}", a, insn);
+/// Prints out loadables, decoding the words that the analysis found to be code
+/// and marking where basic blocks start (and where they go).
+///
+/// If there are symbols, labels are printed beside the addresses they belong to
+/// and PC-relative instructions are annotated with the label they refer to.
+fn print_loadable(loadable: &[Loadable], symbols: &SymbolTable, analysis: &Analysis) {
+    let label = |addr| {
+        if symbols.is_empty() {
+            String::new()
         } else {
-            println!("{:04X}: {:04X}", a, w);
+            let label = symbols
+                .label_for(addr)
+                .map(|l| format!("{}:", l))
+                .unwrap_or_default();
+            format!("{:<16} ", label)
+        }
+    };
+
+    loadable.iter().for_each(|&(a, w)| {
+        if let Some(block) = analysis.block_at(a) {
+            let successors: Vec<String> = block
+                .successors
+                .iter()
+                .map(|e| format!("x{:04X}", e.to))
+                .collect();
+
+            match successors.as_slice() {
+                [] => println!("      ; block {}", range(&(block.start..=block.end))),
+                _ => println!(
+                    "      ; block {} -> {}",
+                    range(&(block.start..=block.end)),
+                    successors.join(", ")
+                ),
+            }
+        }
+
+        match Instruction::try_from(w) {
+            Ok(insn) if analysis.is_code(a) => {
+                let target = pc_relative_target(a, insn)
+                    .and_then(|t| symbols.label_for(t))
+                    .map(|l| format!("  ; {}", l))
+                    .unwrap_or_default();
+
+                println!("{:04X}: {}{}{}", a, label(a), insn, target);
+            }
+            _ => println!("{:04X}: {}{:04X}", a, label(a), w),
         }
     })
 }
@@ -130,91 +449,595 @@ impl If for bool {
     }
 }
 
-fn try_format<F: ObjFileFormat, P: Copy + AsRef<Path>>(
-    path: P,
-    verbose: bool,
-) -> std::io::Result<F::Parsed> {
-    if !F::file_matches_format(&mut File::open(path)?) {
-        return Err(std::io::Error::new(
-            std::io::ErrorKind::InvalidData,
-            "Can't parse with this object file format.",
+/// Makes sure the program's words can go where they're supposed to.
+///
+/// Wraparound and overlapping words are always errors. Words in system space
+/// only matter when the program is being layered onto an OS and words on the
+/// device register page only matter for memory images; both are errors unless
+/// overwriting the OS is allowed.
+fn check_placement(
+    loadables: &[Loadable],
+    to: Format,
+    onto_os: bool,
+    allow_os_overwrite: bool,
+) -> IoResult<()> {
+    let mut errors = Vec::new();
+
+    for problem in validation::check(loadables) {
+        match problem {
+            Problem::SystemSpace(_) if !(to.is_image() && onto_os) => {}
+            Problem::DeviceRegisters(_) if !to.is_image() => {}
+            Problem::SystemSpace(_) | Problem::DeviceRegisters(_) if allow_os_overwrite => {
+                eprintln!("Warning: {}.", problem)
+            }
+            Problem::SystemSpace(_) | Problem::DeviceRegisters(_) => errors.push(format!(
+                "{} (pass `--allow-os-overwrite` to allow this)",
+                problem
+            )),
+            Problem::Wraparound | Problem::Overlap(_) => errors.push(problem.to_string()),
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!(
+                "The program can't be loaded as is:\n  {}",
+                errors.join("\n  ")
+            ),
+        ))
+    }
+}
+
+/// Picks the format that best matches the input, warning if others come
+/// close.
+fn detect_format(path: &str, bytes: &[u8]) -> IoResult<Format> {
+    let detection = Detection::run_on(bytes);
+    let format = match (detection.best(), detection.weak_match()) {
+        (Some(format), _) => format,
+        (None, Some((format, confidence))) => {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "`{}` only looks a little like {} ({}). Use `--from {}` if that's right.",
+                    path,
+                    format.description(),
+                    confidence,
+                    format.name(),
+                ),
+            ))
+        }
+        (None, None) => {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("`{}` isn't in any format obj-conv knows about.", path),
+            ))
+        }
+    };
+
+    let contenders = detection.contenders();
+    if !contenders.is_empty() {
+        eprintln!(
+            "Warning: `{}` could also be: {}; going with {}. Use `--from` to pick another format.",
+            path,
+            contenders
+                .iter()
+                .map(|(f, c)| format!("{} ({})", f.name(), c))
+                .collect::<Vec<_>>()
+                .join(", "),
+            format.name(),
+        );
+    }
+
+    Ok(format)
+}
+
+/// Warns if the input looks like it's in some other format than the one its
+/// extension says it's in.
+fn check_extension(path: &str, bytes: &[u8], format: Format) {
+    let detection = Detection::run_on(bytes);
+    let matches = detection.best() == Some(format)
+        || detection.contenders().iter().any(|(f, _)| *f == format);
+
+    if let (Some(detected), false) = (detection.best(), matches) {
+        eprintln!(
+            "Warning: `{}` looks like {}, not {}; going by its extension. Use `--from {}` if that's wrong.",
+            path,
+            detected.description(),
+            format.description(),
+            detected.name(),
+        );
+    }
+}
+
+/// Reads the input in the given format or, if there isn't one, in the format
+/// its extension is for (warning if the input looks like something else) or
+/// else the format that best matches the input. Returns the format that was
+/// used along with the input.
+///
+/// Unless `quiet` (i.e. when printing JSON or a diff), says what the input was
+/// parsed as (and prints the listing, if there is one). Warnings from parsing
+/// go to stderr either way.
+fn read_input(
+    path: &str,
+    bytes: &[u8],
+    format: Option<Format>,
+    opts: ReadOptions,
+    quiet: bool,
+) -> IoResult<(Format, Input)> {
+    let format = match (format, Format::from_extension(path)) {
+        (Some(format), _) => format,
+        (None, Some(format)) => {
+            check_extension(path, bytes, format);
+            format
+        }
+        (None, None) => detect_format(path, bytes)?,
+    };
+
+    let parsed = read_as(bytes, path, format, opts)?;
+    parsed
+        .warnings
+        .iter()
+        .for_each(|w| eprintln!("Warning: `{}`: {}.", path, w));
+
+    (!quiet).t(|| println!("Parsed as {}:", format.description()));
+    if let Some(listing) = parsed.listing {
+        println!("{}", listing);
+    }
+
+    Ok((format, parsed.input))
+}
+
+fn parse_addr(addr: &str) -> IoResult<Addr> {
+    let a = addr.trim();
+    let a = a.trim_start_matches("0x").trim_start_matches('x');
+
+    Addr::from_str_radix(a, 16).map_err(|e| {
+        Error::new(
+            ErrorKind::InvalidInput,
+            format!("Invalid address `{}`: {}", addr, e),
+        )
+    })
+}
+
+/// The extra entry points (`--entry`) to use for analysis.
+fn parse_entries(matches: &ArgMatches<'_>) -> IoResult<Vec<Addr>> {
+    matches
+        .values_of("entry")
+        .map_or(Ok(Vec::new()), |e| e.map(parse_addr).collect())
+}
+
+fn parse_region(region: &str) -> IoResult<RangeInclusive<Addr>> {
+    match region.splitn(2, '-').collect::<Vec<_>>().as_slice() {
+        [start, end] => Ok(parse_addr(start)?..=parse_addr(end)?),
+        _ => Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!("Expected a region like `3000-FDFF`; got `{}`.", region),
+        )),
+    }
+}
+
+fn output_format(path: &str, format: Option<Format>) -> IoResult<Format> {
+    if let Some(format) = format {
+        return Ok(format);
+    }
+
+    if path == STDIO {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "Pick a format with `--to` when writing to stdout.",
         ));
     }
 
-    let returned = F::parse(&mut File::open(path)?)?;
+    match Format::candidates_for_extension(path).as_slice() {
+        [format] => Ok(*format),
+        // For compatibility, files with unknown extensions are memory dumps:
+        [] => Ok(Format::MemoryDump),
+        candidates => Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!(
+                "`{}` could be any of: {}; please pick one with `--to`.",
+                path,
+                candidates
+                    .iter()
+                    .map(|f| f.name())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        )),
+    }
+}
 
-    println!("Parsed as {}:", F::NAME);
-    verbose.t(|| println!("{}", returned));
+/// Writes the symbol table out next to outputs that can't hold it, unless
+/// that's where the symbol table came from.
+fn write_symbols(path: &str, symbols: &SymbolTable, symbols_path: Option<&str>) -> IoResult<()> {
+    let sym_path = Path::new(path).with_extension("sym");
 
-    verbose.t(|| {
-        let loadable_iter = returned.clone().into().into_iter();
-        println!("As a loadable:");
-        print_loadable(loadable_iter);
+    let same_file = symbols_path.is_some_and(|p| {
+        match (Path::new(p).canonicalize(), sym_path.canonicalize()) {
+            (Ok(a), Ok(b)) => a == b,
+            _ => false,
+        }
     });
 
-    Ok(returned.into())
+    if symbols.is_empty() || same_file {
+        return Ok(());
+    }
+
+    symbols.write(&mut File::create(&sym_path)?)?;
+
+    println!("Wrote out the symbol table ({}).", sym_path.display());
+    Ok(())
 }
 
-enum OsStrategy<'a> {
-    Default,
-    Custom(&'a str),
-    None,
+/// Writes the source lines out in a debug info sidecar next to memory images,
+/// which have nowhere else to put them.
+fn write_debug_info(
+    path: &str,
+    format: Format,
+    source_lines: &BTreeMap<Addr, SourceLine>,
+) -> IoResult<()> {
+    if !format.is_image() || source_lines.values().all(|l| l.text.trim().is_empty()) {
+        return Ok(());
+    }
+
+    let dbg_path = Path::new(path).with_extension("dbg");
+    debug_info::write(source_lines, &mut File::create(&dbg_path)?)?;
+
+    println!("Wrote out debug info ({}).", dbg_path.display());
+    Ok(())
 }
 
-impl<'a> OsStrategy<'a> {
-    fn new(with_os: bool, custom_path: Option<&'a str>) -> Self {
-        use OsStrategy::*;
+/// Reads an input into memory, from stdin if the path is [`STDIO`](STDIO).
+fn open(path: &str) -> IoResult<Vec<u8>> {
+    if path == STDIO {
+        let mut bytes = Vec::new();
+        let _ = io::stdin().read_to_end(&mut bytes)?;
 
-        match (with_os, custom_path) {
-            (false, _) => None,
-            (true, Option::None) => Default,
-            (true, Some(path)) => Custom(path),
-        }
+        Ok(bytes)
+    } else {
+        fs::read(path)
     }
+}
 
-    fn make_memory_dump(self) -> MemoryDump {
-        use OsStrategy::*;
+/// Stdin can only be read once, so only one input can come from it.
+fn check_stdin_once(paths: &[&str]) -> IoResult<()> {
+    if paths.iter().filter(|p| **p == STDIO).count() > 1 {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!("Only one input can be read from stdin (`{}`).", STDIO),
+        ));
+    }
 
-        match self {
-            Default => OS_IMAGE.clone(),
-            Custom(ref path) => FileBackedMemoryShim::from_existing_file(path)
-                .expect("a valid OS image")
-                .into(),
-            None => MemoryDump::blank(),
-        }
+    Ok(())
+}
+
+/// Says what was written where.
+fn report_written(format: Format, paths: &[PathBuf]) {
+    match paths {
+        [_] => println!("Wrote out {}.", format.description()),
+        paths => paths
+            .iter()
+            .for_each(|p| println!("Wrote out {} ({}).", format.description(), p.display())),
     }
 }
 
-fn main() -> IoResult<()> {
+fn main() {
     let matches = args().get_matches();
-    let input_path = matches
-        .value_of("input")
-        .expect("input object file is required");
+
+    let result = match matches.subcommand() {
+        // `diff` follows diff(1): 0 if the inputs are the same, 1 if they
+        // differ, and 2 if something went wrong.
+        ("diff", Some(matches)) => match run_diff(matches) {
+            Ok(true) => return,
+            Ok(false) => process::exit(1),
+            Err(err) => {
+                eprintln!("Error: {}", err);
+                process::exit(2);
+            }
+        },
+        ("inspect", Some(matches)) => run_inspect(matches),
+        _ => run(&matches),
+    };
+
+    if let Err(err) = result {
+        eprintln!("Error: {}", err);
+        process::exit(1);
+    }
+}
+
+/// Drops the words of an input that are outside of the region being
+/// converted.
+///
+/// Memory dumps have the OS in them; unless asked otherwise, just take the
+/// user space part. Since they cover all of memory, the zeros around the
+/// program in the region are dropped too.
+fn restrict_to_region(object: &mut Object, format: Format, region: Option<RangeInclusive<Addr>>) {
+    let region = match (region, format) {
+        (Some(region), _) => Some(region),
+        (None, Format::MemoryDump) => Some(USER_SPACE),
+        (None, _) => None,
+    };
+
+    if let Some(region) = region {
+        object.retain_region(region);
+    }
+
+    if format == Format::MemoryDump {
+        object.trim_zeros();
+    }
+}
+
+/// Reads an input that has to be a program on its own (i.e. for comparing or
+/// inspecting), keeping just the words in the region.
+fn read_program(
+    path: &str,
+    bytes: &[u8],
+    from: Option<Format>,
+    region: Option<RangeInclusive<Addr>>,
+    opts: ReadOptions,
+    quiet: bool,
+) -> IoResult<(Format, Object)> {
+    let (format, input) = read_input(path, bytes, from, opts, quiet)?;
+    let mut object = match input {
+        Input::Program(object) => object,
+        Input::Relocatable(_) => {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("`{}` has to be linked first.", path),
+            ))
+        }
+    };
+
+    restrict_to_region(&mut object, format, region);
+    Ok((format, object))
+}
+
+/// Prints out a summary of an input, as a table or as JSON.
+fn run_inspect(matches: &ArgMatches<'_>) -> IoResult<()> {
+    let path = matches.value_of("input").expect("input is required");
+    let from: Option<Format> = matches.value_of("from").map(|f| f.parse().unwrap());
+    let region = matches.value_of("region").map(parse_region).transpose()?;
+    let json = matches.is_present("json");
+
+    let opts = ReadOptions {
+        lenient: matches.is_present("lenient"),
+        listing: false,
+    };
+
+    let bytes = open(path)?;
+    let (format, object) = read_program(path, &bytes, from, region, opts, json)?;
+    let version = match format {
+        Format::Lc3Tools => Lc3Tools::version(&bytes),
+        _ => None,
+    };
+
+    let entries = parse_entries(matches)?;
+    let analysis = Analysis::run(&object.loadables, &entries);
+    let info = Info::new(path, format, version, &object, &analysis);
+
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    let written = if json {
+        let json = serde_json::to_string_pretty(&info).map_err(Error::other)?;
+        writeln!(out, "{}", json)
+    } else {
+        write!(out, "{}", info)
+    };
+
+    // Piping into something that stops reading early (like `head`) is fine:
+    match written {
+        Err(err) if err.kind() == ErrorKind::BrokenPipe => Ok(()),
+        written => written,
+    }
+}
+
+/// Compares two inputs, printing the differences. Returns whether the inputs
+/// are the same.
+fn run_diff(matches: &ArgMatches<'_>) -> IoResult<bool> {
+    let from: Option<Format> = matches.value_of("from").map(|f| f.parse().unwrap());
+    let region = matches.value_of("region").map(parse_region).transpose()?;
+
+    let opts = ReadOptions {
+        lenient: matches.is_present("lenient"),
+        listing: false,
+    };
+
+    let load = |path| -> IoResult<Object> {
+        read_program(path, &open(path)?, from, region.clone(), opts, true).map(|(_, object)| object)
+    };
+    let old_path = matches.value_of("old").expect("old input is required");
+    let new_path = matches.value_of("new").expect("new input is required");
+    check_stdin_once(&[old_path, new_path])?;
+
+    let old = load(old_path)?;
+    let new = load(new_path)?;
+
+    let hunks = diff::diff(&old.loadables, &new.loadables);
+    hunks.iter().for_each(|h| print!("{}", h));
+
+    let count = |kind| -> usize {
+        hunks
+            .iter()
+            .filter(|h| h.kind == kind)
+            .map(|h| h.words.len())
+            .sum()
+    };
+    let (added, removed, changed) = (
+        count(ChangeKind::Added),
+        count(ChangeKind::Removed),
+        count(ChangeKind::Changed),
+    );
+
+    if hunks.is_empty() {
+        println!("No differences.");
+    } else {
+        println!(
+            "{} words differ: {} added, {} removed, {} changed.",
+            added + removed + changed,
+            added,
+            removed,
+            changed
+        );
+    }
+
+    Ok(hunks.is_empty())
+}
+
+fn run(matches: &ArgMatches<'_>) -> IoResult<()> {
+    let input_paths: Vec<&str> = matches
+        .values_of("input")
+        .expect("input object file is required")
+        .collect();
+    check_stdin_once(&input_paths)?;
+
+    if matches.is_present("detect") {
+        return input_paths.iter().try_for_each(|path| {
+            (input_paths.len() > 1).t(|| println!("{}:", path));
+            print!("{}", Detection::run_on(&open(path)?));
+            Ok(())
+        });
+    }
+
     let output_path = matches
         .value_of("output")
         .expect("output object file is required");
 
-    let verbose = matches.is_present("verbose");
+    let from: Option<Format> = matches.value_of("from").map(|f| f.parse().unwrap());
+    let to = output_format(
+        output_path,
+        matches.value_of("to").map(|f| f.parse().unwrap()),
+    )?;
+
+    let layout: Layout = matches.value_of("layout").unwrap().parse().unwrap();
+
+    // When the output goes to stdout, nothing else can:
+    let to_stdout = output_path == STDIO;
+    let verbose = matches.is_present("verbose") && !to_stdout;
+    let allow_lossy = matches.is_present("allow-lossy");
 
     let with_os = !matches.is_present("without-os");
     let custom_os_path = matches.value_of("custom-os");
 
-    let program: Vec<Loadable> = try_format::<Lc3Tools<'_>, _>(input_path, verbose)
-        .map(|l| l.into_iter().collect())
-        .or_else(|_| {
+    let region = matches.value_of("region").map(parse_region).transpose()?;
+
+    let opts = ReadOptions {
+        lenient: matches.is_present("lenient"),
+        listing: verbose,
+    };
+
+    let mut layers = Layers::default();
+    let mut relocatables = Vec::new();
+
+    for path in input_paths.iter() {
+        let (format, input) = read_input(path, &open(path)?, from, opts, to_stdout)?;
+        let mut object = match input {
+            Input::Program(object) => object,
+            Input::Relocatable(obj) => {
+                relocatables.push((path.to_string(), obj));
+                continue;
+            }
+        };
+
+        restrict_to_region(&mut object, format, region.clone());
+        layers.add(path, object);
+    }
+
+    // Relocatable inputs go wherever the other inputs aren't:
+    if !relocatables.is_empty() {
+        let base = matches.value_of("base").map_or(Ok(0x3000), parse_addr)?;
+
+        linker::link(&relocatables, base, &|addr| layers.is_used(addr))
+            .map_err(|err| {
+                Error::new(ErrorKind::InvalidInput, format!("Linking failed: {}.", err))
+            })?
+            .into_iter()
+            .for_each(|(path, object)| layers.add(&path, object));
+    }
+
+    if input_paths.len() > 1 {
+        layers
+            .conflicts()
+            .iter()
+            .for_each(|c| eprintln!("Warning: {}.", c));
+
+        (!to_stdout).t(|| {
+            println!("Layout:");
+            layers
+                .ownership()
+                .iter()
+                .for_each(|(r, name)| println!("  {:<12} {}", range(r), name));
+        });
+    }
+
+    let mut object = layers.into_object();
+
+    if let Some(path) = matches.value_of("symbols") {
+        object
+            .symbols
+            .merge(SymbolTable::parse(&mut File::open(path)?)?);
+    }
+
+    let entries = parse_entries(matches)?;
+    let analysis = Analysis::run(&object.loadables, &entries);
+
+    verbose.t(|| {
+        println!("As a loadable:");
+        print_loadable(&object.loadables, &object.symbols, &analysis);
+    });
+
+    // Symbols that the output can't hold go next to it instead (if it isn't
+    // going to stdout):
+    let symbols = if to.drops_symbols() && !to_stdout {
+        mem::take(&mut object.symbols)
+    } else {
+        SymbolTable::default()
+    };
+
+    let lost = object.lost_in(to);
+    if !lost.is_empty() {
+        let lost = lost.join(" and ");
+
+        if allow_lossy {
             eprintln!(
-                "Failed to parse as {}; trying to parse as {}:",
-                Lc3Tools::NAME,
-                <&Lumetta>::NAME
+                "Warning: {} can't hold the input's {}; dropping them.",
+                to.description(),
+                lost
             );
-            try_format::<&Lumetta, _>(input_path, verbose).map(|l| l.into_iter().collect())
-        })?;
+        } else {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "Converting to {} would drop the input's {}; pass `--allow-lossy` to do so anyway.",
+                    to.description(),
+                    lost
+                ),
+            ));
+        }
+    }
+
+    check_placement(
+        &object.loadables,
+        to,
+        with_os,
+        matches.is_present("allow-os-overwrite"),
+    )?;
+
+    let os = OsStrategy::new(with_os, custom_os_path);
+
+    // There's nowhere to put the symbol table or debug info:
+    if to_stdout {
+        let stdout = io::stdout();
+        return output::write_to(&mut stdout.lock(), to, object, os, layout, &analysis);
+    }
 
-    let mut image: MemoryDump = OsStrategy::new(with_os, custom_os_path).make_memory_dump();
+    let source_lines = object.source_lines.clone();
 
-    let _ = image.layer_loadable(program);
+    let written = output::write(output_path, to, object, os, layout, &analysis)?;
+    report_written(to, &written);
 
-    FileBackedMemoryShim::with_initialized_memory(output_path, image)
-        .flush()
-        .map_err(|_| std::io::Error::last_os_error())
+    write_symbols(output_path, &symbols, matches.value_of("symbols"))?;
+    write_debug_info(output_path, to, &source_lines)
 }